@@ -0,0 +1,50 @@
+//!
+//! Runs the tasks of osmain as a linux program on top of the hosted
+//! port of the kernel, see `rt::generic::hosted`. The uart output
+//! appears on stdout, quit with ctrl-c:
+//!
+//! cargo run -p hosted
+//!
+//! The task sets of the qemu tests get selected by the name of their
+//! osmain feature and end the program with their exit code:
+//!
+//! cargo run -p hosted -- test-order
+//!
+use rt::generic::hosted;
+use rt::interrupts;
+use rt::sched::scheduler;
+use userspace::testsets;
+
+fn create_tasks(set: Option<&str>) {
+    let tasks: &[fn()] = match set {
+        None => &[
+            userspace::user::context2,
+            userspace::user::context3,
+            userspace::user::context1,
+            userspace::user::context0,
+            userspace::user::button,
+        ],
+        Some("test-sleep") => &testsets::sleep::TASKS,
+        Some("test-order") => &testsets::order::TASKS,
+        Some(other) => {
+            eprintln!("unknown task set {}", other);
+            std::process::exit(2);
+        }
+    };
+    for task in tasks {
+        hosted::create(*task);
+    }
+}
+
+fn main() {
+    let set = std::env::args().nth(1);
+    hosted::boot();
+
+    create_tasks(set.as_deref());
+
+    scheduler::load();
+
+    interrupts::systick::STK::set_up_systick(4);
+
+    hosted::run()
+}
@@ -0,0 +1,28 @@
+//!
+//! Runs the task sets of `userspace::testsets` on the hosted port,
+//! the same checks as the qemu images get.
+//!
+use qemutest::{check_round_robin, run_program};
+use std::process::Command;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+fn run(set: &str) -> qemutest::Run {
+    let mut hosted = Command::new(env!("CARGO_BIN_EXE_hosted"));
+    hosted.arg(set);
+    run_program(hosted, TIMEOUT)
+}
+
+#[test]
+fn tasks_run_in_round_robin_order() {
+    check_round_robin(&run("test-order"));
+}
+
+#[test]
+fn sleeping_task_wakes_up_three_times() {
+    let run = run("test-sleep");
+    assert_eq!(run.exit_code, Some(0), "output:\n{}", run.output());
+    assert!(run.find("sleep start").is_some(), "output:\n{}", run.output());
+    assert_eq!(run.all("wake up!").len(), 3, "output:\n{}", run.output());
+}
@@ -0,0 +1,22 @@
+#!/bin/bash
+# runs the os on an emulated stm32f405 (netduinoplus2), no board needed.
+# the uart output appears on the terminal, quit with ctrl-a x.
+# pass -s -S to wait for gdb on port 1234. extra features, e.g.
+# FEATURES=semihosting ./qemuRun.sh, get added to the build.
+# BOARD=microbit ./qemuRun.sh runs the cortex-m0 port on an emulated
+# bbc micro:bit instead.
+case "$BOARD" in
+microbit)
+	TARGET=thumbv6m-none-eabi
+	MACHINE=microbit
+	;;
+*)
+	BOARD=qemu
+	TARGET=thumbv7em-none-eabihf
+	MACHINE=netduinoplus2
+	;;
+esac
+cargo b --target $TARGET --features "$BOARD $FEATURES" || exit 1
+qemu-system-arm -M $MACHINE -nographic \
+-semihosting-config enable=on,target=native \
+-kernel ./target/$TARGET/debug/osmain "$@"
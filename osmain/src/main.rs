@@ -1,24 +1,38 @@
 #![no_std]
 #![no_main]
-#![feature(asm)]
 extern crate rt;
 extern crate userspace;
 
-use core::ptr::addr_of;
 use rt::interrupts;
-use rt::sys::call_api::println;
-use rt::sched::{process, scheduler, task_control};
+use rt::sched::{process, scheduler};
+
+#[cfg(not(any(feature = "test-sleep", feature = "test-order")))]
+fn create_tasks() {
+    process::create(userspace::user::context2 as *const () as u32);
+    process::create(userspace::user::context3 as *const () as u32);
+    process::create(userspace::user::context1 as *const () as u32);
+    process::create(userspace::user::context0 as *const () as u32);
+    process::create(userspace::user::button as *const () as u32);
+}
+
+// task sets of the qemu integration tests
+#[cfg(feature = "test-sleep")]
+fn create_tasks() {
+    for task in userspace::testsets::sleep::TASKS.iter() {
+        process::create(*task as *const () as u32);
+    }
+}
+
+#[cfg(feature = "test-order")]
+fn create_tasks() {
+    for task in userspace::testsets::order::TASKS.iter() {
+        process::create(*task as *const () as u32);
+    }
+}
 
 #[no_mangle]
 pub fn main() -> ! {
-    let process_1 = process::ProcessFrame::new(userspace::user::context2 as *const () as u32);
-    let process_2 = process::ProcessFrame::new(userspace::user::context3 as *const () as u32);
-    let process_3 = process::ProcessFrame::new(userspace::user::context1 as *const () as u32);
-    let process_4 = process::ProcessFrame::new(userspace::user::context0 as *const () as u32);
-    task_control::insert(addr_of!(process_1.r4) as u32);
-    task_control::insert(addr_of!(process_2.r4) as u32);
-    task_control::insert(addr_of!(process_3.r4) as u32);
-    task_control::insert(addr_of!(process_4.r4) as u32);
+    create_tasks();
 
     scheduler::load();
 
@@ -27,10 +41,3 @@ pub fn main() -> ! {
 
     loop {}
 }
-
-#[allow(non_snake_case)]
-#[no_mangle]
-pub fn HardFault(_ef: *const u32) -> ! {
-    println("---- !!!KERNEL PANIC!!! ---");
-    loop {}
-}
@@ -0,0 +1,200 @@
+//!
+//! Runs the os in qemu and collects its uart output. A test image is
+//! osmain built with one of its `test-*` features, which replaces the
+//! regular tasks by a task set of `userspace::testsets`. The task set
+//! ends the emulator through semihosting with its exit code. The
+//! hosted port runs the same task sets as a linux program.
+//!
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const QEMU: &str = "qemu-system-arm";
+
+///
+/// An emulated board, the qemu machine and the board feature
+/// and target osmain gets built with.
+///
+pub struct Machine {
+    pub name: &'static str,
+    pub board: &'static str,
+    pub target: &'static str,
+}
+
+// stm32f405, cortex-m4
+pub const NETDUINOPLUS2: Machine = Machine {
+    name: "netduinoplus2",
+    board: "qemu",
+    target: "thumbv7em-none-eabihf",
+};
+
+// nrf51822, cortex-m0
+pub const MICROBIT: Machine = Machine {
+    name: "microbit",
+    board: "microbit",
+    target: "thumbv6m-none-eabi",
+};
+
+///
+/// A line printed by the os and when it arrived, relative to the start of qemu.
+///
+pub struct Line {
+    pub at: Duration,
+    pub text: String,
+}
+
+pub struct Run {
+    pub lines: Vec<Line>,
+    // None if qemu got killed after the timeout
+    pub exit_code: Option<i32>,
+}
+
+impl Run {
+    ///
+    /// Position of the first line equal to `text`.
+    ///
+    pub fn find(&self, text: &str) -> Option<usize> {
+        self.lines.iter().position(|l| l.text == text)
+    }
+
+    ///
+    /// All lines equal to `text`.
+    ///
+    pub fn all(&self, text: &str) -> Vec<&Line> {
+        self.lines.iter().filter(|l| l.text == text).collect()
+    }
+
+    ///
+    /// The output, for assertion messages.
+    ///
+    pub fn output(&self) -> String {
+        self.lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+pub fn qemu_available() -> bool {
+    Command::new(QEMU).arg("--version").output().is_ok()
+}
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+///
+/// Builds osmain for `machine` with `feature` into a target directory of
+/// its own, so several images can get built in parallel. Returns the elf file.
+///
+pub fn build_image(machine: &Machine, feature: &str) -> PathBuf {
+    let target_dir = root().join("target").join("qemutest").join(format!("{}-{}", machine.name, feature));
+    let status = Command::new("cargo")
+        .args(["build", "--target", machine.target])
+        .args(["--features", &format!("{} {}", machine.board, feature), "--target-dir"])
+        .arg(&target_dir)
+        .current_dir(root().join("osmain"))
+        .status()
+        .expect("cargo not found");
+    assert!(status.success(), "building the test image {} for {} failed", feature, machine.name);
+    target_dir.join(machine.target).join("debug").join("osmain")
+}
+
+///
+/// Boots `image` and collects the uart output until the os exits
+/// or `timeout` elapsed.
+///
+pub fn run_image(machine: &Machine, image: &Path, timeout: Duration) -> Run {
+    let mut qemu = Command::new(QEMU);
+    qemu.args(["-M", machine.name, "-display", "none", "-monitor", "none", "-serial", "stdio"])
+        .args(["-semihosting-config", "enable=on,target=native"])
+        .arg("-kernel")
+        .arg(image);
+    run_program(qemu, timeout)
+}
+
+///
+/// Starts `command`, e.g. qemu or the hosted port, and collects its
+/// output until it exits or `timeout` elapsed.
+///
+pub fn run_program(mut command: Command, timeout: Duration) -> Run {
+    let mut program = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start the program");
+
+    let start = Instant::now();
+    let stdout = program.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // the os ends its lines with \n\r
+        for line in BufReader::new(stdout).split(b'\n') {
+            match line {
+                Ok(l) => {
+                    let text = String::from_utf8_lossy(&l).trim_matches(|c| c == '\r' || c == '\0').to_string();
+                    if sender.send(Line { at: start.elapsed(), text }).is_err() {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    let exit_code = loop {
+        match program.try_wait().expect("failed to wait for the program") {
+            Some(status) => break status.code(),
+            None if start.elapsed() > timeout => {
+                let _ = program.kill();
+                let _ = program.wait();
+                break None;
+            }
+            None => thread::sleep(Duration::from_millis(20)),
+        }
+    };
+    // the reader ends as soon as the program closed the pipe
+    let lines = receiver.iter().collect();
+    Run { lines, exit_code }
+}
+
+///
+/// Builds and boots the test image of `feature` on the netduinoplus2.
+/// Panics if qemu is not installed, the tests are ignored by default
+/// and only run on request with `cargo test -p qemutest -- --ignored`.
+///
+pub fn run(feature: &str, timeout: Duration) -> Run {
+    run_on(&NETDUINOPLUS2, feature, timeout)
+}
+
+pub fn run_on(machine: &Machine, feature: &str, timeout: Duration) -> Run {
+    assert!(qemu_available(), "{} not found, it is needed to run {}", QEMU, feature);
+    let image = build_image(machine, feature);
+    run_image(machine, &image, timeout)
+}
+
+///
+/// Checks the output of the task set "test-order": every task prints
+/// its three rounds in order and none of them gets ahead of the others.
+///
+pub fn check_round_robin(run: &Run) {
+    assert_eq!(run.exit_code, Some(0), "output:\n{}", run.output());
+
+    let tasks = ["A", "B", "C"];
+    let mut rounds = [0usize; 3];
+    for line in &run.lines {
+        let task = match tasks.iter().position(|t| line.text.len() == 2 && line.text.starts_with(t)) {
+            Some(t) => t,
+            None => continue,
+        };
+        // every task prints its rounds in order
+        assert_eq!(line.text, format!("{}{}", tasks[task], rounds[task]), "output:\n{}", run.output());
+        rounds[task] += 1;
+        // and none of them gets ahead by more than two rounds. a task
+        // the systick preempts between printing and yielding spends its
+        // next turn on the pending yield, so the others may print twice.
+        let slowest = rounds.iter().min().unwrap();
+        assert!(rounds[task] - slowest <= 2, "{} got ahead, output:\n{}", tasks[task], run.output());
+    }
+    assert_eq!(rounds, [3, 3, 3], "output:\n{}", run.output());
+}
@@ -0,0 +1,40 @@
+//!
+//! Boots the task sets of `userspace::testsets` in qemu.
+//!
+use qemutest::{check_round_robin, run, run_on, MICROBIT};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+#[test]
+#[ignore = "needs qemu-system-arm, run with --ignored"]
+fn sleeping_task_wakes_up_in_time() {
+    let run = run("test-sleep", TIMEOUT);
+    assert_eq!(run.exit_code, Some(0), "output:\n{}", run.output());
+
+    let start = run.find("sleep start").expect("sleeper did not start");
+    let wake_ups = run.all("wake up!");
+    assert_eq!(wake_ups.len(), 3, "output:\n{}", run.output());
+
+    // each sleep lasts 200 ms, the emulator may be slower but not faster
+    let mut last = run.lines[start].at;
+    for line in wake_ups {
+        let slept = line.at - last;
+        assert!(slept >= Duration::from_millis(150), "woke up after {:?}", slept);
+        assert!(slept <= Duration::from_millis(2000), "woke up after {:?}", slept);
+        last = line.at;
+    }
+}
+
+#[test]
+#[ignore = "needs qemu-system-arm, run with --ignored"]
+fn tasks_run_in_round_robin_order() {
+    check_round_robin(&run("test-order", TIMEOUT));
+}
+
+// the same scheduler on the cortex-m0 port
+#[test]
+#[ignore = "needs qemu-system-arm, run with --ignored"]
+fn armv6m_tasks_run_in_round_robin_order() {
+    check_round_robin(&run_on(&MICROBIT, "test-order", TIMEOUT));
+}
@@ -11,11 +11,108 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // put `link.x` in the build directory
     File::create(out_dir.join("link.x"))?.write_all(include_bytes!("link.x"))?;
-    // assemble the `asm.s` file
-    Build::new().file("./src/asm/dispatch_mng.s").compile("asm"); // <- NEW!
+    println!("cargo:rerun-if-changed=link.x");
 
-    // rebuild if `asm.s` changed
-    println!("cargo:rerun-if-changed=asm.s"); // <- NEW!
+    // memory layout of the board, gets included by `link.x`.
+    // see `src/generic/board.rs` for the boards.
+    let boards: Vec<&str> = ["f303", "f407", "qemu", "microbit"]
+        .iter()
+        .copied()
+        .filter(|b| env::var_os(format!("CARGO_FEATURE_{}", b.to_uppercase())).is_some())
+        .collect();
+    let (flash_origin, flash_size, sram_size) = match boards.as_slice() {
+        // stm32f407vet6, the 64k of ccm ram are left out
+        ["f407"] => (0x0800_0000, 512 * 1024, 128 * 1024),
+        // stm32f405 of the qemu machine netduinoplus2
+        ["qemu"] => (0x0800_0000, 1024 * 1024, 128 * 1024),
+        // nrf51822 of the bbc micro:bit, flash starts at 0
+        ["microbit"] => (0x0000_0000, 256 * 1024, 16 * 1024),
+        // stm32f303vc of the discovery board, also without a board feature
+        ["f303"] | [] => (0x0800_0000, 32 * 1024, 40 * 1024),
+        _ => return Err(format!("only one board can be selected, got {}", boards.join(" and ")).into()),
+    };
+    File::create(out_dir.join("memory.x"))?.write_all(
+        format!(
+            "MEMORY\n{{\n  FLASH (rx) : ORIGIN = {:#010x}, LENGTH = {}\n  SRAM (rwx) : ORIGIN = 0x20000000, LENGTH = {}\n}}\n",
+            flash_origin, flash_size, sram_size
+        )
+        .as_bytes(),
+    )?;
+
+    // the hosted port emulates the peripherals of the stm32 boards
+    if env::var_os("CARGO_FEATURE_HOSTED").is_some() && boards == ["microbit"] {
+        return Err("the hosted port does not support the microbit board".into());
+    }
+
+    // the 16k sram of the micro:bit do not fit the default tables
+    let small = sram_size < 32 * 1024;
+
+    // sizes of the kernel tables, see `generic::config`
+    let max_tasks = config_value("RT_MAX_TASKS", 8)?;
+    let task_stack_size = config_value("RT_TASK_STACK_SIZE", if small { 1024 } else { 2048 })?;
+    let kernel_stack_size = config_value("RT_KERNEL_STACK_SIZE", if small { 1024 } else { 2048 })?;
+    let heap_size = config_value("RT_HEAP_SIZE", if small { 2048 } else { 8192 })?;
+    // the last stack belongs to the idle task
+    if max_tasks < 2 {
+        return Err("RT_MAX_TASKS must be at least 2, one task is the idle task".into());
+    }
+    // every stack and the heap form a region of the mpu
+    if !task_stack_size.is_power_of_two() || task_stack_size < 256 {
+        return Err("RT_TASK_STACK_SIZE must be a power of two and at least 256 bytes".into());
+    }
+    if !heap_size.is_power_of_two() || heap_size < 256 {
+        return Err("RT_HEAP_SIZE must be a power of two and at least 256 bytes".into());
+    }
+
+    File::create(out_dir.join("config.rs"))?.write_all(
+        format!(
+            "pub const MAX_TASKS: usize = {};\npub const TASK_STACK_SIZE: usize = {};\npub const HEAP_SIZE: usize = {};\n\
+             pub const FLASH_SIZE: usize = {};\npub const SRAM_SIZE: usize = {};\n",
+            max_tasks, task_stack_size, heap_size, flash_size, sram_size
+        )
+        .as_bytes(),
+    )?;
+    // gets included by `link.x`
+    File::create(out_dir.join("config.x"))?
+        .write_all(
+            format!(
+                "_kernel_stack_size = {};\n_task_stack_size = {};\n_heap_size = {};\n",
+                kernel_stack_size, task_stack_size, heap_size
+            )
+            .as_bytes(),
+        )?;
+
+    // assemble the `asm.s` file. host builds for `cargo test`
+    // use the stand-ins of `src/host.rs` instead. cortex-m0/m0+
+    // (thumbv6m) lack the thumb-2 instructions of the cortex-m4 file.
+    let target = env::var("TARGET")?;
+    let armv6m = env::var_os("CARGO_FEATURE_ARMV6M").is_some();
+    if target.starts_with("thumb") {
+        if armv6m != target.starts_with("thumbv6m") {
+            return Err(format!(
+                "target {} does not match the core of the board, armv6m boards need thumbv6m-none-eabi",
+                target
+            )
+            .into());
+        }
+        match armv6m {
+            true => Build::new().file("./src/asm/dispatch_mng_v6m.s").compile("asm"),
+            false => Build::new().file("./src/asm/dispatch_mng.s").compile("asm"),
+        }
+    }
+
+    // rebuild if the assembler files changed
+    println!("cargo:rerun-if-changed=src/asm");
 
     Ok(())
 }
+
+// reads a size from the environment of the build, e.g.
+// `RT_MAX_TASKS=4 cargo build`, and falls back to `default`
+fn config_value(name: &str, default: usize) -> Result<usize, Box<dyn Error>> {
+    println!("cargo:rerun-if-env-changed={}", name);
+    match env::var(name) {
+        Ok(value) => Ok(value.parse().map_err(|_| format!("{} must be a number", name))?),
+        Err(_) => Ok(default),
+    }
+}
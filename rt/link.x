@@ -1,14 +1,10 @@
 
 /* generall memory info; manual cortex m4 page 30 */
-MEMORY
-{
-  /* see page 53 at https://www.st.com/resource/en/datasheet/stm32f303vc.pdf */
-  /* flash area starts @0800000 */
-  FLASH (rx) : ORIGIN = 0x08000000, LENGTH = 32K
-
-  /* also sram area starts @20000000 */
-  SRAM (rwx) : ORIGIN = 0x20000000, LENGTH = 40K
-}
+/* The MEMORY block with the regions FLASH and SRAM depends on the board,
+see build.rs. For the stm32f303vc see page 53 at
+https://www.st.com/resource/en/datasheet/stm32f303vc.pdf: flash area starts
+@0800000, sram area starts @20000000. */
+INCLUDE memory.x
 
 /* The Entry section expects the symbol name of the first executable 
 piece of code which will be loaded into the processor. Logically it is
@@ -24,6 +20,10 @@ into two symbol names because of their return type. See lib.rs
 for more info. */
 EXTERN(RESET); 
 EXTERN(EXCEPTIONS);
+EXTERN(INTERRUPTS);
+
+/* Build time configuration, generated by build.rs */
+INCLUDE config.x
 
 /* Description of what the memory contains and how it will be located . */
 SECTIONS
@@ -40,16 +40,19 @@ SECTIONS
 
     /* Entry 2: Any other exception handlers. */
     KEEP(*(.vector_table.exceptions));
+
+    /* Entry 16: Peripheral interrupts, all pointing to the dispatcher. */
+    KEEP(*(.vector_table.interrupts));
   } > FLASH
 
 
   PROVIDE(NMI = DefaultExceptionHandler);
-  PROVIDE(HardFault = DefaultExceptionHandler);
-  PROVIDE(MemManage = DefaultExceptionHandler);
-  PROVIDE(BusFault = DefaultExceptionHandler);
-  PROVIDE(UsageFault = DefaultExceptionHandler);
+  PROVIDE(HardFault = __fault_entry);
+  PROVIDE(MemManage = __fault_entry);
+  PROVIDE(BusFault = __fault_entry);
+  PROVIDE(UsageFault = __fault_entry);
   PROVIDE(SVCall = DefaultExceptionHandler);
-  PROVIDE(PendSV = DefaultExceptionHandler);
+  PROVIDE(PendSV = __pendsv_entry);
 
   .text :
   {
@@ -62,6 +65,27 @@ SECTIONS
   } > FLASH
 
 
+  /* The sections with the largest alignment come first,
+  so no SRAM gets wasted for padding. */
+
+  /* Kernel heap, see mem::heap. Aligned to its size, so the mpu
+  can open it for the tasks as one region. */
+  .kernel_heap (NOLOAD) : ALIGN(_heap_size)
+  {
+    _sheap = .;
+    KEEP(*(.kernel_heap .kernel_heap.*));
+    _eheap = .;
+  } > SRAM
+
+  /* One stack per task, see sched::process. Aligned to the stack size,
+  so every stack can be covered by a region of the mpu. */
+  .task_stacks (NOLOAD) : ALIGN(_task_stack_size)
+  {
+    _stask_stacks = .;
+    KEEP(*(.task_stacks .task_stacks.*));
+    _etask_stacks = .;
+  } > SRAM
+
   .bss :
   {
     _sbss = .;
@@ -78,6 +102,28 @@ SECTIONS
 
   _sidata = LOADADDR(.data);
 
+  /* Process table, see sched::task_control. Entries get written on task creation. */
+  .kernel_tcbs (NOLOAD) : ALIGN(4)
+  {
+    _stcbs = .;
+    KEEP(*(.kernel_tcbs .kernel_tcbs.*));
+    _etcbs = .;
+  } > SRAM
+
+  /* Neither zeroed nor initialized at startup, keeps its
+  content across a soft reset (e.g. the crash record). */
+  .noinit (NOLOAD) :
+  {
+    *(.noinit .noinit.*);
+    _enoinit = .;
+  } > SRAM
+
+  /* The kernel stack grows downwards from the end of SRAM towards
+  the sections above, so it needs some space left over. */
+  _kernel_stack_start = ORIGIN(SRAM) + LENGTH(SRAM) - _kernel_stack_size;
+  ASSERT(_enoinit <= _kernel_stack_start, "
+  ERROR: task stacks and kernel tables do not fit into SRAM,
+  reduce RT_MAX_TASKS, RT_TASK_STACK_SIZE, RT_HEAP_SIZE or RT_KERNEL_STACK_SIZE");
 
   /DISCARD/ :
   {
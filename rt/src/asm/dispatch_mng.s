@@ -2,11 +2,21 @@
 .global __save_process_context
 .global __load_process_context
 .global __write_psp
-.global __return_to_user_mode
+.global __pendsv_entry
 .global __instant_start
 .global __trap
 .global __get_r0
+.global __fault_entry
+.global __disable_interrupts
+.global __drop_privileges
+.global __enter_critical
+.global __exit_critical
+.global __in_unprivileged_thread
+.global __wait_for_interrupt
+.global __data_barrier
+.global __semihosting_call
 .cpu cortex-m4
+.fpu fpv4-sp-d16
 .syntax unified
 .thumb
 
@@ -14,20 +24,72 @@ __get_current_psp:
 	mrs r0, psp
 	bx lr
 
+@ r0: exc_return of the interrupted task. bit 4 cleared means
+@ the task used the fpu, so s16 - s31 need to be saved as well
 __save_process_context:
-	mrs r0, psp
-	stmdb r0!, {r4-r11}
-	msr psp, r0
+	mrs r1, psp
+	tst r0, #0x10
+	it eq
+	vstmdbeq r1!, {s16-s31}
+	stmdb r1!, {r4-r11}
+	msr psp, r1
 	bx lr
 
 __get_r0:
 	bx lr
 
+@ r0: stack pointer of the task, r1: its exc_return
 __load_process_context:
 	ldmfd r0!, {r4-r11}
+	tst r1, #0x10
+	it eq
+	vldmiaeq r0!, {s16-s31}
 	msr psp, r0
 	bx lr
 
+__disable_interrupts:
+	cpsid i
+	bx lr
+
+@ masks all interrupts, returns the previous primask
+__enter_critical:
+	mrs r0, primask
+	cpsid i
+	bx lr
+
+@ r0: primask returned by __enter_critical
+__exit_critical:
+	msr primask, r0
+	bx lr
+
+@ returns 1 for a task, which can not mask interrupts itself
+__in_unprivileged_thread:
+	mrs r0, ipsr
+	cbnz r0, 1f
+	mrs r0, control
+	and r0, r0, #1
+	bx lr
+1:
+	movs r0, #0
+	bx lr
+
+@ sets control.npriv, thread mode runs unprivileged afterwards
+__drop_privileges:
+	mrs r0, control
+	orr r0, r0, #1
+	msr control, r0
+	isb
+	bx lr
+
+__wait_for_interrupt:
+	wfi
+	bx lr
+
+@ returns once every outstanding memory access completed
+__data_barrier:
+	dsb
+	bx lr
+
 __instant_start:
 	bx pc
 
@@ -35,14 +97,29 @@ __trap:
 	svc 0
 	bx lr
 
+@ r0: operation, r1: adress of its parameter block.
+@ only usable with a debugger or an emulator attached,
+@ otherwise the breakpoint escalates to a hardfault.
+__semihosting_call:
+	bkpt 0xAB
+	bx lr
+
 __write_psp:
 	msr psp, r0
 	bx lr
 
-__return_to_user_mode:
-	mrs r0, msp
-	sub r4, r7, r0
-	add r4, #0x04
-	mov r1, #0xfffffffd
-	str r1, [sp, r4]
-	bx lr
\ No newline at end of file
+@ passes the exc_return value of pendsv to ContextSwitch and
+@ returns from the exception with the one it hands back
+__pendsv_entry:
+	mov r0, lr
+	bl ContextSwitch
+	bx r0
+
+.thumb_func
+__fault_entry:
+	tst lr, #4
+	ite eq
+	mrseq r0, msp
+	mrsne r0, psp
+	mov r1, lr
+	b FaultHandler
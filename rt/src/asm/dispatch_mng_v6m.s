@@ -0,0 +1,160 @@
+@ armv6-m (cortex-m0/m0+) variant of dispatch_mng.s. the thumb-1
+@ instruction set can neither store r8 - r11 with stm nor push onto
+@ a descending stack with stmdb, the cores have no fpu.
+@ the layout of a saved context matches the cortex-m4 one: r4 at
+@ the lowest adress, r11 at the highest.
+.global __get_current_psp
+.global __save_process_context
+.global __load_process_context
+.global __write_psp
+.global __pendsv_entry
+.global __trap
+.global __get_r0
+.global __fault_entry
+.global __disable_interrupts
+.global __drop_privileges
+.global __enter_critical
+.global __exit_critical
+.global __in_unprivileged_thread
+.global __wait_for_interrupt
+.global __data_barrier
+.global __semihosting_call
+.cpu cortex-m0
+.syntax unified
+.thumb
+
+.thumb_func
+__get_current_psp:
+	mrs r0, psp
+	bx lr
+
+@ r0: exc_return of the interrupted task, unused without fpu.
+@ r4 - r7 are needed to move r8 - r11, the caller gets them back.
+.thumb_func
+__save_process_context:
+	push {r4-r7}
+	mrs r1, psp
+	subs r1, #32
+	msr psp, r1
+	stmia r1!, {r4-r7}
+	mov r4, r8
+	mov r5, r9
+	mov r6, r10
+	mov r7, r11
+	stmia r1!, {r4-r7}
+	pop {r4-r7}
+	bx lr
+
+.thumb_func
+__get_r0:
+	bx lr
+
+@ r0: stack pointer of the task, r1: its exc_return, unused without fpu
+.thumb_func
+__load_process_context:
+	adds r0, #16
+	ldmia r0!, {r4-r7}
+	mov r8, r4
+	mov r9, r5
+	mov r10, r6
+	mov r11, r7
+	subs r0, #32
+	ldmia r0!, {r4-r7}
+	adds r0, #16
+	msr psp, r0
+	bx lr
+
+.thumb_func
+__disable_interrupts:
+	cpsid i
+	bx lr
+
+@ masks all interrupts, returns the previous primask
+.thumb_func
+__enter_critical:
+	mrs r0, primask
+	cpsid i
+	bx lr
+
+@ r0: primask returned by __enter_critical
+.thumb_func
+__exit_critical:
+	msr primask, r0
+	bx lr
+
+@ returns 1 for a task, which can not mask interrupts itself.
+@ a cortex-m0 has no unprivileged mode, control.npriv reads as 0.
+.thumb_func
+__in_unprivileged_thread:
+	mrs r0, ipsr
+	cmp r0, #0
+	bne 1f
+	mrs r0, control
+	movs r1, #1
+	ands r0, r1
+	bx lr
+1:
+	movs r0, #0
+	bx lr
+
+@ sets control.npriv, only effective on a cortex-m0+
+.thumb_func
+__drop_privileges:
+	mrs r0, control
+	movs r1, #1
+	orrs r0, r1
+	msr control, r0
+	isb
+	bx lr
+
+.thumb_func
+__wait_for_interrupt:
+	wfi
+	bx lr
+
+@ returns once every outstanding memory access completed
+.thumb_func
+__data_barrier:
+	dsb
+	bx lr
+
+.thumb_func
+__trap:
+	svc 0
+	bx lr
+
+@ r0: operation, r1: adress of its parameter block.
+@ only usable with a debugger or an emulator attached,
+@ otherwise the breakpoint escalates to a hardfault.
+.thumb_func
+__semihosting_call:
+	bkpt 0xAB
+	bx lr
+
+.thumb_func
+__write_psp:
+	msr psp, r0
+	bx lr
+
+@ passes the exc_return value of pendsv to ContextSwitch and
+@ returns from the exception with the one it hands back
+.thumb_func
+__pendsv_entry:
+	mov r0, lr
+	bl ContextSwitch
+	bx r0
+
+@ the fault handler may be out of reach of a thumb-1 branch
+.thumb_func
+__fault_entry:
+	mov r1, lr
+	movs r0, #4
+	tst r0, r1
+	bne 1f
+	mrs r0, msp
+	b 2f
+1:
+	mrs r0, psp
+2:
+	ldr r2, =FaultHandler
+	bx r2
@@ -1,109 +1,949 @@
-use super::mem;
 use super::platform;
+#[cfg(not(feature = "microbit"))]
 use super::cpu;
-pub mod tim3 {
-    use crate::generic::platform::bitfields;
+///
+/// Driver of the general purpose and advanced timers, tim1 - tim17.
+/// The instance gets selected by the `Tim` it is created with:
+///
+/// let timer = TimerX::new(tim::TIM4);
+/// timer.enable_clock();
+/// timer.set_prescaler(7);
+/// timer.set_arr(1000);
+/// timer.set_mode(Mode::Periodic);
+/// timer.start();
+///
+#[cfg(not(feature = "microbit"))]
+pub mod timer {
+    use super::platform::registers::rcc;
+    use super::platform::registers::tim::{self, Bus, Tim};
+    use crate::generic::reg::Field;
 
-    use super::mem::memory_handler::{read, write};
-    use super::platform::{adresses, offsets};
-    const TIM3_BASE: u32 = adresses::TIM3_BASEADRESS;
-    use core::ptr;
+    pub enum Mode {
+        // the counter stops at the next update event
+        OneShot,
+        // the counter restarts from 0 after each update event
+        Periodic,
+    }
 
-    pub fn start() {
-        write(TIM3_BASE, read(TIM3_BASE) | bitfields::tim::CEN);
+    // edges of the input a capture channel reacts on
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum Edge {
+        Rising,
+        Falling,
+        Both,
     }
 
-    pub fn clear_udis() {
-        write(TIM3_BASE, read(TIM3_BASE) & !(bitfields::tim::UDIS));
+    pub struct TimerX {
+        tim: Tim,
     }
 
-    pub fn flush() {
-        let timx_cnt: u32 = TIM3_BASE | offsets::tim::CNT;
-        write(timx_cnt, read(timx_cnt) & !(0xFFFF));
+    impl TimerX {
+        pub const fn new(tim: Tim) -> TimerX {
+            TimerX { tim }
+        }
+
+        pub fn instance(&self) -> &Tim {
+            &self.tim
+        }
+
+        // bits of the counter and the reload value
+        fn counter(&self) -> Field {
+            Field::new(0, self.tim.width)
+        }
+
+        pub fn enable_clock(&self) {
+            match self.tim.bus {
+                Bus::Apb1 => rcc::APB1ENR.set(Field::bit(self.tim.bit)),
+                Bus::Apb2 => rcc::APB2ENR.set(Field::bit(self.tim.bit)),
+            }
+        }
+
+        ///
+        /// Puts every register of the timer back into its reset state.
+        ///
+        pub fn reset(&self) {
+            let rstr = match self.tim.bus {
+                Bus::Apb1 => rcc::APB1RSTR,
+                Bus::Apb2 => rcc::APB2RSTR,
+            };
+            rstr.set(Field::bit(self.tim.bit));
+            rstr.clear(Field::bit(self.tim.bit));
+        }
+
+        ///
+        /// The counter clock is the timer clock divided by `value + 1`.
+        /// A new value takes effect with the next update, see `update`.
+        ///
+        pub fn set_prescaler(&self, value: u16) {
+            self.tim.psc().write_field(tim::PRESCALER, value as u32);
+        }
+
+        ///
+        /// The counter runs from 0 to `reload`, surplus bits of a 16 bit
+        /// timer get cut off.
+        ///
+        pub fn set_arr(&self, reload: u32) {
+            self.tim.arr().write_field(self.counter(), reload);
+        }
+
+        pub fn set_mode(&self, mode: Mode) {
+            match mode {
+                Mode::OneShot => self.tim.cr1().set(tim::OPM),
+                Mode::Periodic => self.tim.cr1().clear(tim::OPM),
+            }
+        }
+
+        pub fn start(&self) {
+            self.tim.cr1().set(tim::CEN);
+        }
+
+        pub fn stop(&self) {
+            self.tim.cr1().clear(tim::CEN);
+        }
+
+        pub fn is_running(&self) -> bool {
+            self.tim.cr1().is_set(tim::CEN)
+        }
+
+        ///
+        /// Generates an update event, which clears the counter and loads
+        /// prescaler and reload value. Raises no interrupt as long as
+        /// the update interrupt is enabled through this driver.
+        ///
+        pub fn update(&self) {
+            self.tim.egr().write(tim::UG.mask());
+        }
+
+        pub fn counter_value(&self) -> u32 {
+            self.tim.cnt().read_field(self.counter())
+        }
+
+        pub fn set_counter(&self, value: u32) {
+            self.tim.cnt().write_field(self.counter(), value);
+        }
+
+        ///
+        /// Value the counter gets compared with, channels 1 - 4.
+        ///
+        pub fn set_compare(&self, channel: u8, value: u32) {
+            if channel == 0 || channel > self.tim.channels {
+                return;
+            }
+            self.tim.ccr(channel).write_field(self.counter(), value);
+        }
+
+        ///
+        /// Turns `channel` into an input which captures the counter on
+        /// the given edges of its own pin. `filter` (0 - 15) selects how
+        /// long an edge has to be stable, `divider` (1, 2, 4 or 8) how
+        /// many edges make one capture. False for invalid arguments.
+        /// The channel stays disabled until `enable_capture`.
+        ///
+        pub fn set_input_capture(&self, channel: u8, edge: Edge, filter: u8, divider: u8) -> bool {
+            let prescaler = match self.capture_prescaler(channel, filter, divider) {
+                Some(p) => p,
+                None => return false,
+            };
+            // cc_select can only be written while the channel is off
+            self.disable_capture(channel);
+            let ccmr = self.tim.ccmr(channel);
+            ccmr.write_field(tim::cc_select(channel), 0b01);
+            ccmr.write_field(tim::ic_prescaler(channel), prescaler);
+            ccmr.write_field(tim::ic_filter(channel), filter as u32);
+            self.set_capture_edge(channel, edge);
+            true
+        }
+
+        ///
+        /// True if `set_input_capture` accepts the arguments, without
+        /// touching the timer.
+        ///
+        pub fn can_capture(&self, channel: u8, filter: u8, divider: u8) -> bool {
+            self.capture_prescaler(channel, filter, divider).is_some()
+        }
+
+        // ic prescaler field for `divider`, None for invalid arguments
+        fn capture_prescaler(&self, channel: u8, filter: u8, divider: u8) -> Option<u32> {
+            if channel == 0 || channel > self.tim.channels || filter > 15 {
+                return None;
+            }
+            match divider {
+                1 => Some(0b00),
+                2 => Some(0b01),
+                4 => Some(0b10),
+                8 => Some(0b11),
+                _ => None,
+            }
+        }
+
+        pub fn set_capture_edge(&self, channel: u8, edge: Edge) {
+            if channel == 0 || channel > self.tim.channels {
+                return;
+            }
+            let ccer = self.tim.ccer();
+            match edge {
+                Edge::Rising => {
+                    ccer.clear(tim::cc_polarity(channel));
+                    ccer.clear(tim::ccn_polarity(channel));
+                }
+                Edge::Falling => {
+                    ccer.set(tim::cc_polarity(channel));
+                    ccer.clear(tim::ccn_polarity(channel));
+                }
+                Edge::Both => {
+                    ccer.set(tim::cc_polarity(channel));
+                    ccer.set(tim::ccn_polarity(channel));
+                }
+            }
+        }
+
+        pub fn enable_capture(&self, channel: u8) {
+            if channel == 0 || channel > self.tim.channels {
+                return;
+            }
+            self.tim.ccer().set(tim::cc_enable(channel));
+        }
+
+        pub fn disable_capture(&self, channel: u8) {
+            if channel == 0 || channel > self.tim.channels {
+                return;
+            }
+            self.tim.ccer().clear(tim::cc_enable(channel));
+        }
+
+        ///
+        /// Counter value of the last capture. Reading it clears the
+        /// compare flag of the channel.
+        ///
+        pub fn capture_value(&self, channel: u8) -> u32 {
+            if channel == 0 || channel > self.tim.channels {
+                return 0;
+            }
+            self.tim.ccr(channel).read_field(self.counter())
+        }
+
+        ///
+        /// Raises the update interrupt when the counter reaches the
+        /// reload value. A software update (ug) does not trigger it.
+        ///
+        pub fn enable_update_interrupt(&self) {
+            self.tim.cr1().set(tim::URS);
+            self.tim.cr1().clear(tim::UDIS);
+            self.tim.dier().set(tim::UIE);
+        }
+
+        pub fn disable_update_interrupt(&self) {
+            self.tim.dier().clear(tim::UIE);
+        }
+
+        pub fn enable_compare_interrupt(&self, channel: u8) {
+            if channel == 0 || channel > self.tim.channels {
+                return;
+            }
+            self.tim.dier().set(tim::cc(channel));
+        }
+
+        pub fn disable_compare_interrupt(&self, channel: u8) {
+            if channel == 0 || channel > self.tim.channels {
+                return;
+            }
+            self.tim.dier().clear(tim::cc(channel));
+        }
+
+        pub fn is_update_pending(&self) -> bool {
+            self.tim.sr().is_set(tim::UIF)
+        }
+
+        pub fn is_compare_pending(&self, channel: u8) -> bool {
+            if channel == 0 || channel > self.tim.channels {
+                return false;
+            }
+            self.tim.sr().is_set(tim::cc(channel))
+        }
+
+        ///
+        /// Writing 0 clears a flag and written ones have no effect,
+        /// so no other flag gets lost on the way.
+        ///
+        pub fn clear_update_flag(&self) {
+            self.tim.sr().write(!tim::UIF.mask());
+        }
+
+        pub fn clear_compare_flag(&self, channel: u8) {
+            if channel == 0 || channel > self.tim.channels {
+                return;
+            }
+            self.tim.sr().write(!tim::cc(channel).mask());
+        }
+    }
+
+    ///
+    /// The kernel runs timer 2 for `measurement` and timer 3 as
+    /// `sleep_timer`, drivers refuse to take them over.
+    ///
+    pub fn is_reserved(tim: &Tim) -> bool {
+        tim.base == tim::TIM2.base || tim.base == tim::TIM3.base
+    }
+}
+
+///
+/// Timer 3 wakes up the sleeping task, it runs once per sleep
+/// and raises its update interrupt at the end.
+///
+#[cfg(not(feature = "microbit"))]
+pub mod sleep_timer {
+    use super::platform::registers::tim;
+    use super::timer::{Mode, TimerX};
+
+    const TIMER: TimerX = TimerX::new(tim::TIM3);
+
+    pub const IRQ: usize = tim::TIM3.irq;
+
+    pub fn init(prescaler: u16) {
+        TIMER.set_prescaler(prescaler);
+        TIMER.set_mode(Mode::OneShot);
+        TIMER.update();
+        TIMER.clear_update_flag();
+        TIMER.enable_update_interrupt();
     }
 
-    pub fn clear_uif() {
-        let tim3_sr = TIM3_BASE | offsets::tim::SR;
-        let existing_value = read(tim3_sr);
-        write(tim3_sr, existing_value & !(0b1111));
-        write(tim3_sr, existing_value & !(0b1 << 6));
+    ///
+    /// The interrupt fires after `ticks` counts of the prescaled clock.
+    ///
+    pub fn start(ticks: u16) {
+        TIMER.set_arr(ticks as u32);
+        TIMER.update();
+        TIMER.start();
     }
 
-    pub fn set_ug() {
-        let tim3_egr = TIM3_BASE | offsets::tim::EGR;
-        write(tim3_egr, read(tim3_egr) | bitfields::tim::UG);
+    ///
+    /// Gets called by the interrupt, the timer stands still afterwards.
+    ///
+    pub fn acknowledge() {
+        TIMER.stop();
+        TIMER.clear_update_flag();
+        TIMER.set_counter(0);
     }
+}
 
-    pub fn stop() {
-        write(TIM3_BASE, read(TIM3_BASE) & !(bitfields::tim::CEN));
+// timer0 of the nrf51 takes the role of timer 3. it stops and
+// raises its interrupt once the counter reaches the reload value.
+#[cfg(feature = "microbit")]
+pub mod sleep_timer {
+    use super::platform::irqn;
+    use super::platform::registers::timer;
+
+    pub const IRQ: usize = irqn::TIMER0;
+
+    ///
+    /// The clock gets divided by `prescaler + 1` like on the stm32,
+    /// rounded down to the next power of two. Updates can not be
+    /// disabled on the nrf51.
+    ///
+    pub fn init(prescaler: u16) {
+        let exponent = 31 - (prescaler as u32 + 1).leading_zeros();
+        timer::PRESCALER.write_field(timer::SCALE, exponent.min(9));
+        timer::CLEAR.write(1);
+        timer::COMPARE0.write(0);
+        timer::MODE.write(timer::TIMER_MODE);
+        timer::BITMODE.write(timer::BITS_16);
+        timer::SHORTS.write(timer::COMPARE0_CLEAR.mask() | timer::COMPARE0_STOP.mask());
+        timer::INTENSET.write(timer::COMPARE0_INT.mask());
     }
 
-    pub fn enable_interrupt() {
-        let tim3_dier: u32 = TIM3_BASE | offsets::tim::DIER;
-        write(tim3_dier, read(tim3_dier) | bitfields::tim::CC1IE);
+    pub fn start(ticks: u16) {
+        timer::CC0.write(ticks as u32);
+        timer::CLEAR.write(1);
+        timer::START.write(1);
     }
 
-    pub fn set_ccr(threshold: u16) {
-        let tim3_ccr1: u32 = TIM3_BASE | offsets::tim::CCR1;
-        write(tim3_ccr1, read(tim3_ccr1) | threshold as u32);
+    pub fn acknowledge() {
+        timer::STOP.write(1);
+        timer::COMPARE0.write(0);
+        timer::CLEAR.write(1);
     }
+}
 
-    pub fn set_prescaler(value: u16) {
-        let tim3_psc: u32 = TIM3_BASE | offsets::tim::PSC;
-        write(tim3_psc, read(tim3_psc) | value as u32);
+///
+/// Timer 2 measures the time between the start and stop
+/// syscalls in microseconds, for at most 71 minutes.
+///
+#[cfg(not(feature = "microbit"))]
+pub mod measurement {
+    use super::platform::registers::tim;
+    use super::timer::{Mode, TimerX};
+    use crate::generic::board::timing;
+
+    const TIMER: TimerX = TimerX::new(tim::TIM2);
+
+    pub fn start() {
+        TIMER.stop();
+        TIMER.set_prescaler(timing::US_PRESCALER);
+        TIMER.set_arr(u32::MAX);
+        TIMER.set_mode(Mode::Periodic);
+        TIMER.update();
+        TIMER.start();
     }
-    pub fn reset_timer() {
-        // TIM3 RESET -> p 166
-        let rcc_apb1rstr: u32 = 0x4002_1000 | 0x10;
-        let existing_value = read(rcc_apb1rstr);
-        write(rcc_apb1rstr, existing_value | 0b10);
-        let existing_value = read(rcc_apb1rstr);
-        write(rcc_apb1rstr, existing_value & !(0b10));
+
+    ///
+    /// Stops the measurement and returns the elapsed microseconds.
+    ///
+    pub fn stop() -> u32 {
+        TIMER.stop();
+        TIMER.counter_value()
     }
-    pub fn read_value() -> u32 {
-        let timx_cnt: u32 = TIM3_BASE | 0x24;
-        unsafe { (ptr::read_volatile(timx_cnt as *mut u32) & !(0b1 << 31)) * 125 }
+}
+
+// the nrf51 has no timer to spare
+#[cfg(feature = "microbit")]
+pub mod measurement {
+    pub fn start() {}
+
+    pub fn stop() -> u32 {
+        0
     }
 }
 
-pub mod tim2 {
-    use super::mem::memory_handler::{read, write};
-    use super::platform::{adresses, offsets};
+///
+/// Pulse width modulation on the channels of a timer, e.g. for motors
+/// or the brightness of leds. The kernel configures the timer and its
+/// channels, tasks may change the duty cycle of enabled channels
+/// through `sys::call_api::pwm_set_duty`:
+///
+/// let pwm = Pwm::new(tim::TIM4);
+/// pwm.init(20_000);
+/// pwm.bind("D", 12, 2);
+/// pwm.enable(1, Polarity::ActiveHigh);
+/// pwm.set_duty(1, 250);
+///
+/// Timer 2 and 3 belong to `measurement` and `sleep_timer`.
+///
+#[cfg(not(feature = "microbit"))]
+pub mod pwm {
+    use super::gpio;
+    use super::gpio_types::SpeedTypes;
+    use super::platform::registers::tim::{self, Tim};
+    use super::timer::{self, Mode, TimerX};
+    use crate::generic::board::BOARD;
+    use crate::generic::reg::Field;
 
-    const TIM2_CR1: u32 = adresses::TIM2_BASEADRESS;
-    use core::ptr;
+    // duty cycles are given in per mille
+    pub const DUTY_MAX: u32 = 1000;
 
-    pub fn start_measurement() {
-        write(TIM2_CR1, read(TIM2_CR1) | 0b1);
+    pub enum Polarity {
+        // the output is high for the duty cycle
+        ActiveHigh,
+        // the output is low for the duty cycle
+        ActiveLow,
     }
-    pub fn stop_measurement() {
-        write(TIM2_CR1, read(TIM2_CR1) & !(0b1));
+
+    pub struct Pwm {
+        timer: TimerX,
     }
-    pub fn reset_timer() {
-        // TIM2 RESET -> p 166
-        let rcc_apb1rstr: u32 = adresses::RCC | offsets::rcc::RCC_APB1RSTR;
-        unsafe {
-            let existing_value = ptr::read_volatile(rcc_apb1rstr as *mut u32);
-            ptr::write_volatile(rcc_apb1rstr as *mut u32, existing_value | 0b1);
-            let existing_value = ptr::read_volatile(rcc_apb1rstr as *mut u32);
-            ptr::write_volatile(rcc_apb1rstr as *mut u32, existing_value & !(0b1));
+
+    impl Pwm {
+        pub const fn new(tim: Tim) -> Pwm {
+            Pwm { timer: TimerX::new(tim) }
+        }
+
+        fn tim(&self) -> &Tim {
+            self.timer.instance()
+        }
+
+        fn has_channel(&self, channel: u8) -> bool {
+            channel != 0 && channel <= self.tim().channels
+        }
+
+        fn counter(&self) -> Field {
+            Field::new(0, self.tim().width)
+        }
+
+        // counts of one period
+        fn period(&self) -> u64 {
+            self.tim().arr().read_field(self.counter()) as u64 + 1
+        }
+
+        ///
+        /// Starts the timer with all channels disabled. The main
+        /// output of an advanced timer gets enabled as well.
+        /// False for the timers of the kernel, see `timer::is_reserved`.
+        ///
+        pub fn init(&self, frequency: u32) -> bool {
+            if timer::is_reserved(self.tim()) {
+                return false;
+            }
+            self.timer.enable_clock();
+            self.timer.stop();
+            self.tim().cr1().set(tim::ARPE);
+            self.timer.set_mode(Mode::Periodic);
+            self.set_frequency(frequency);
+            if self.tim().advanced {
+                self.tim().bdtr().set(tim::MOE);
+            }
+            self.timer.start();
+            true
+        }
+
+        ///
+        /// Picks the smallest prescaler whose period fits the counter,
+        /// for the finest steps of the duty cycle. The duty cycles of
+        /// the channels stay the same. Frequencies below the timer
+        /// clock divided by 2^32 get clamped to the slowest possible.
+        ///
+        pub fn set_frequency(&self, frequency: u32) {
+            let ticks = match frequency {
+                0 => u32::MAX,
+                f => (BOARD.timer_clock / f).max(1),
+            };
+            let range = 1u64 << self.tim().width;
+            let prescaler = ((ticks as u64 - 1) / range).min(u16::MAX as u64);
+            let reload = (ticks as u64 / (prescaler + 1)).clamp(1, range) - 1;
+
+            let old_period = self.period();
+            for channel in 1..=self.tim().channels {
+                let ccr = self.tim().ccr(channel);
+                let compare = ccr.read_field(self.counter()) as u64;
+                ccr.write_field(self.counter(), (compare * (reload + 1) / old_period) as u32);
+            }
+            self.timer.set_prescaler(prescaler as u16);
+            self.timer.set_arr(reload as u32);
+            self.timer.update();
+        }
+
+        ///
+        /// Connects a pin to the timer, `af` is the alternate function
+        /// of the timer on this pin. False if the pin does not exist or
+        /// got taken by another driver or task.
+        ///
+        pub fn bind(&self, port: &str, pin: u8, af: u32) -> bool {
+            match gpio::take_alternate(port, pin, af) {
+                Some(driver) => {
+                    driver.set_ospeedr(SpeedTypes::High);
+                    true
+                }
+                None => false,
+            }
+        }
+
+        ///
+        /// Drives the output of `channel` in pwm mode 1. A new duty
+        /// cycle takes effect at the start of the next period.
+        ///
+        pub fn enable(&self, channel: u8, polarity: Polarity) {
+            if !self.has_channel(channel) {
+                return;
+            }
+            let ccmr = self.tim().ccmr(channel);
+            ccmr.write_field(tim::cc_select(channel), 0b00);
+            ccmr.write_field(tim::oc_mode(channel), tim::PWM_MODE_1);
+            ccmr.set(tim::oc_preload(channel));
+            match polarity {
+                Polarity::ActiveHigh => self.tim().ccer().clear(tim::cc_polarity(channel)),
+                Polarity::ActiveLow => self.tim().ccer().set(tim::cc_polarity(channel)),
+            }
+            self.tim().ccer().set(tim::cc_enable(channel));
+        }
+
+        ///
+        /// Drives the complementary output of `channel` as well, the
+        /// inverse of the main output with `ActiveHigh`. Only channels
+        /// 1 - 3 of tim1 and tim8 have one, false otherwise.
+        ///
+        pub fn enable_complementary(&self, channel: u8, polarity: Polarity) -> bool {
+            if !self.tim().advanced || channel == 0 || channel > 3 {
+                return false;
+            }
+            match polarity {
+                Polarity::ActiveHigh => self.tim().ccer().clear(tim::ccn_polarity(channel)),
+                Polarity::ActiveLow => self.tim().ccer().set(tim::ccn_polarity(channel)),
+            }
+            self.tim().ccer().set(tim::ccn_enable(channel));
+            true
+        }
+
+        pub fn disable(&self, channel: u8) {
+            if !self.has_channel(channel) {
+                return;
+            }
+            self.tim().ccer().clear(tim::cc_enable(channel));
+            if self.tim().advanced && channel <= 3 {
+                self.tim().ccer().clear(tim::ccn_enable(channel));
+            }
+        }
+
+        ///
+        /// True if the kernel configured the channel as pwm output.
+        ///
+        pub fn is_enabled(&self, channel: u8) -> bool {
+            self.has_channel(channel)
+                && self.tim().ccer().is_set(tim::cc_enable(channel))
+                && self.tim().ccmr(channel).read_field(tim::oc_mode(channel)) == tim::PWM_MODE_1
+        }
+
+        ///
+        /// Active share of the period in per mille, values above
+        /// `DUTY_MAX` keep the output active all the time.
+        ///
+        pub fn set_duty(&self, channel: u8, duty: u32) {
+            let compare = self.period() * duty.min(DUTY_MAX) as u64 / DUTY_MAX as u64;
+            self.timer.set_compare(channel, compare as u32);
+        }
+
+        pub fn duty(&self, channel: u8) -> u32 {
+            if !self.has_channel(channel) {
+                return 0;
+            }
+            let compare = self.tim().ccr(channel).read_field(self.counter()) as u64;
+            (compare * DUTY_MAX as u64 / self.period()) as u32
         }
     }
-    pub fn read_value() -> u32 {
-        let timx_cnt: u32 = adresses::TIM2_BASEADRESS | offsets::tim::CNT;
-        (read(timx_cnt) & !(0b1 << 31)) * 125
+
+    ///
+    /// Sets the duty cycle on behalf of a task. Only channels the
+    /// kernel enabled through `Pwm::enable` can be changed.
+    ///
+    pub fn set_duty_of(timer: u32, channel: u32, duty: u32) -> bool {
+        let pwm = match tim::instance(timer) {
+            Some(t) => Pwm::new(t),
+            None => return false,
+        };
+        if channel > 4 || !pwm.is_enabled(channel as u8) {
+            return false;
+        }
+        pwm.set_duty(channel as u8, duty);
+        true
+    }
+}
+
+// the nrf51 has no timer to spare
+#[cfg(feature = "microbit")]
+pub mod pwm {
+    pub const DUTY_MAX: u32 = 1000;
+
+    pub fn set_duty_of(_timer: u32, _channel: u32, _duty: u32) -> bool {
+        false
+    }
+}
+
+///
+/// Input capture on the timer channels, e.g. for encoders or the echo
+/// of ultrasonic sensors. The kernel configures the channels, every
+/// capture gets stored with its time and edge in a small buffer of the
+/// channel. Tasks read pulse width and frequency of the last captures
+/// or block until the next one through the capture syscalls:
+///
+/// capture::init(tim::TIM4, timing::US_PRESCALER);
+/// capture::bind("D", 12, 2);
+/// capture::configure(tim::TIM4, 1, Edge::Both, 3, 1);
+///
+/// The counter of a 16 bit timer gets extended by its overflows, so
+/// timestamps wrap around after 2^32 counts like on the 32 bit timers.
+///
+#[cfg(not(feature = "microbit"))]
+pub mod capture {
+    use super::gpio;
+    use super::platform::registers::tim::{self, Tim};
+    use super::timer::{self, Edge, Mode, TimerX};
+    use crate::generic::board::BOARD;
+    use crate::interrupts::irq;
+    use crate::sched::{critical, event};
+    use core::ptr::addr_of_mut;
+
+    // channels which capture at the same time
+    const SLOTS: usize = 4;
+    // captures buffered per channel
+    const DEPTH: usize = 8;
+
+    #[derive(Clone, Copy)]
+    struct Stamp {
+        time: u32,
+        rising: bool,
+    }
+
+    struct Slot {
+        tim: Tim,
+        channel: u8,
+        edge: Edge,
+        // edges per capture
+        divider: u8,
+        // event flag the waiting tasks block on
+        event: u32,
+        overflows: u32,
+        stamps: [Stamp; DEPTH],
+        // next stamp to write and number of valid stamps
+        head: usize,
+        count: usize,
+    }
+
+    impl Slot {
+        // the n-th newest capture, 0 is the last one
+        fn newest(&self, n: usize) -> Option<Stamp> {
+            match n < self.count {
+                true => Some(self.stamps[(self.head + DEPTH - 1 - n) % DEPTH]),
+                false => None,
+            }
+        }
+
+        fn push(&mut self, stamp: Stamp) {
+            self.stamps[self.head] = stamp;
+            self.head = (self.head + 1) % DEPTH;
+            self.count = (self.count + 1).min(DEPTH);
+        }
+    }
+
+    const UNUSED: Option<Slot> = None;
+    static mut SLOTS_STATE: [Option<Slot>; SLOTS] = [UNUSED; SLOTS];
+
+    fn slots<'a>() -> &'a mut [Option<Slot>; SLOTS] {
+        unsafe { &mut *addr_of_mut!(SLOTS_STATE) }
+    }
+
+    // the interrupt writes the slots, so everyone else
+    // accesses them within a critical section only
+    fn find(timer: u32, channel: u32) -> Option<&'static mut Slot> {
+        let base = tim::instance(timer)?.base;
+        slots().iter_mut().flatten().find(|s| s.tim.base == base && s.channel as u32 == channel)
+    }
+
+    ///
+    /// Lets the counter of `tim` run over its full range with the given
+    /// prescaler. Every capture channel of this timer counts with it.
+    /// False for the timers of the kernel, see `timer::is_reserved`.
+    ///
+    pub fn init(tim: Tim, prescaler: u16) -> bool {
+        if timer::is_reserved(&tim) {
+            return false;
+        }
+        let timer = TimerX::new(tim);
+        timer.enable_clock();
+        timer.stop();
+        timer.set_prescaler(prescaler);
+        timer.set_arr(u32::MAX);
+        timer.set_mode(Mode::Periodic);
+        timer.update();
+        timer.clear_update_flag();
+        // the interrupts get enabled by `configure`, the handler
+        // only visits timers with a configured channel
+        timer.start();
+        true
+    }
+
+    ///
+    /// Connects the input pin of a channel, see `pwm::Pwm::bind`.
+    ///
+    pub fn bind(port: &str, pin: u8, af: u32) -> bool {
+        gpio::take_alternate(port, pin, af).is_some()
+    }
+
+    ///
+    /// Captures the counter of `tim` on the given edges of `channel`,
+    /// see `TimerX::set_input_capture` for `filter` and `divider`.
+    /// With `Edge::Both` the channel alternates between rising and
+    /// falling edge, so it knows the level of each pulse. Needs a
+    /// divider of 1 for that. Configuring a channel again drops its
+    /// captures. False if the arguments are invalid, the timer belongs
+    /// to the kernel or all slots or event flags are in use.
+    ///
+    pub fn configure(tim: Tim, channel: u8, edge: Edge, filter: u8, divider: u8) -> bool {
+        if timer::is_reserved(&tim) || edge == Edge::Both && divider != 1 {
+            return false;
+        }
+        let timer = TimerX::new(tim);
+        if !timer.can_capture(channel, filter, divider) {
+            return false;
+        }
+        let same = |s: &Option<Slot>| match s {
+            Some(s) => s.tim.base == tim.base && s.channel == channel,
+            None => false,
+        };
+        let index = match slots().iter().position(same) {
+            Some(i) => i,
+            None => match slots().iter().position(|s| s.is_none()) {
+                Some(i) => i,
+                None => return false,
+            },
+        };
+        let event = match &slots()[index] {
+            Some(s) => s.event,
+            None => match event::allocate() {
+                Some(e) => e,
+                None => return false,
+            },
+        };
+        let first = match edge {
+            Edge::Both => Edge::Rising,
+            e => e,
+        };
+        timer.set_input_capture(channel, first, filter, divider);
+        // the counter overflowed unobserved until now
+        if !slots().iter().flatten().any(|s| s.tim.base == tim.base) {
+            timer.clear_update_flag();
+        }
+        critical::section(|| {
+            slots()[index] = Some(Slot {
+                tim,
+                channel,
+                edge,
+                divider,
+                event,
+                overflows: 0,
+                stamps: [Stamp { time: 0, rising: false }; DEPTH],
+                head: 0,
+                count: 0,
+            })
+        });
+
+        // registering again replaces the handler, so every channel can do it
+        irq::register_irq(tim.irq, handle_timers);
+        irq::register_irq(tim.cc_irq, handle_timers);
+        if tim.width < 32 {
+            timer.enable_update_interrupt();
+        }
+        timer.clear_compare_flag(channel);
+        timer.enable_compare_interrupt(channel);
+        timer.enable_capture(channel);
+        true
+    }
+
+    // handler of the update and capture interrupts of every capture timer
+    fn handle_timers() {
+        for i in 0..SLOTS {
+            let tim = match &slots()[i] {
+                Some(s) => s.tim,
+                None => continue,
+            };
+            // the first slot of a timer handles all of its channels
+            let handled = slots()[..i].iter().flatten().any(|s| s.tim.base == tim.base);
+            if !handled {
+                handle(tim);
+            }
+        }
+    }
+
+    fn handle(tim: Tim) {
+        let timer = TimerX::new(tim);
+        let overflow = timer.is_update_pending();
+        // captures in the lower half of the range were probably taken
+        // after the pending overflow
+        let half = 1u32 << (tim.width - 1);
+
+        for slot in slots().iter_mut().flatten().filter(|s| s.tim.base == tim.base) {
+            if !timer.is_compare_pending(slot.channel) {
+                continue;
+            }
+            let value = timer.capture_value(slot.channel);
+            timer.clear_compare_flag(slot.channel);
+            let overflows = match overflow && value < half {
+                true => slot.overflows.wrapping_add(1),
+                false => slot.overflows,
+            };
+            let time = (((overflows as u64) << tim.width) as u32) | value;
+            let rising = !tim.ccer().is_set(tim::cc_polarity(slot.channel));
+            slot.push(Stamp { time, rising });
+            if slot.edge == Edge::Both {
+                timer.set_capture_edge(
+                    slot.channel,
+                    match rising {
+                        true => Edge::Falling,
+                        false => Edge::Rising,
+                    },
+                );
+            }
+            event::signal(slot.event);
+        }
+        if overflow {
+            timer.clear_update_flag();
+            for slot in slots().iter_mut().flatten().filter(|s| s.tim.base == tim.base) {
+                slot.overflows = slot.overflows.wrapping_add(1);
+            }
+        }
+    }
+
+    // counts of the timer per second
+    fn tick_rate(tim: &Tim) -> u64 {
+        BOARD.timer_clock as u64 / (tim.psc().read_field(tim::PRESCALER) as u64 + 1)
+    }
+
+    ///
+    /// Microseconds between the last falling edge and the rising edge
+    /// before it, the length of the last high pulse. Only channels
+    /// capturing both edges know it.
+    ///
+    pub fn pulse_width(timer: u32, channel: u32) -> Option<u32> {
+        critical::section(|| {
+            let slot = find(timer, channel)?;
+            if slot.edge != Edge::Both {
+                return None;
+            }
+            let n = match slot.newest(0)?.rising {
+                true => 1,
+                false => 0,
+            };
+            let (start, end) = (slot.newest(n + 1)?, slot.newest(n)?);
+            let ticks = end.time.wrapping_sub(start.time) as u64;
+            Some((ticks * 1_000_000 / tick_rate(&slot.tim)) as u32)
+        })
+    }
+
+    ///
+    /// Frequency of the input in hertz, from the two last captures
+    /// of the same edge.
+    ///
+    pub fn frequency(timer: u32, channel: u32) -> Option<u32> {
+        critical::section(|| {
+            let slot = find(timer, channel)?;
+            let previous = match slot.edge {
+                Edge::Both => 2,
+                _ => 1,
+            };
+            let ticks = slot.newest(0)?.time.wrapping_sub(slot.newest(previous)?.time) as u64;
+            match ticks {
+                0 => None,
+                t => Some((tick_rate(&slot.tim) * slot.divider as u64 / t) as u32),
+            }
+        })
+    }
+
+    ///
+    /// The event flag which gets signalled by each capture of the
+    /// channel, None if the kernel did not configure it.
+    ///
+    pub fn event_of(timer: u32, channel: u32) -> Option<u32> {
+        critical::section(|| find(timer, channel).map(|s| s.event))
+    }
+}
+
+// the nrf51 has no timer to spare
+#[cfg(feature = "microbit")]
+pub mod capture {
+    pub fn pulse_width(_timer: u32, _channel: u32) -> Option<u32> {
+        None
+    }
+
+    pub fn frequency(_timer: u32, _channel: u32) -> Option<u32> {
+        None
+    }
+
+    pub fn event_of(_timer: u32, _channel: u32) -> Option<u32> {
+        None
     }
 }
 
 // NOTE: ONLY FOR TESTPURPOSES, NO GENERIC USART DRIVER YET!
 pub mod uart {
-    use super::mem::memory_handler::{read, write};
+    #[cfg(not(feature = "microbit"))]
     use super::cpu::system;
+    #[cfg(not(feature = "microbit"))]
+    use super::platform::registers::usart::{self, Usart};
+    #[cfg(feature = "microbit")]
+    use super::platform::registers::uart as uart0;
 
+    #[cfg(not(feature = "microbit"))]
     const USART1_BASE: u32 = adresses::USART1_BASEADRESS;
-    use core::ptr;
+    #[cfg(feature = "microbit")]
+    const USART1_BASE: u32 = adresses::UART0;
 
-    use crate::generic::platform::{adresses, offsets};
+    use crate::generic::platform::adresses;
     pub struct UsartX {
         usart_base_adress: u32,
         bus_number: u8,
@@ -119,61 +959,203 @@ pub mod uart {
             baudrate,
         }
     }
+    #[cfg(not(feature = "microbit"))]
     impl UsartX {
         pub fn enable(&self) {
-            let usartx_brr = self.usart_base_adress | offsets::usart1::BRR;
+            let usartx = Usart::at(self.usart_base_adress);
             let baudrate_divisor = system::CLOCK / self.baudrate;
             // clk / 9600 baud
-            write(usartx_brr, baudrate_divisor);
+            usartx.brr().write(baudrate_divisor);
 
-            let usart1_cr1 = self.usart_base_adress;
-            let existing_value = read(usart1_cr1);
-            write(usart1_cr1, existing_value | (0b1100));
-            let existing_value = read(usart1_cr1);
-            write(usart1_cr1, existing_value | (0b1));
+            usartx.cr1().modify(|r| r | usart::TE.mask() | usart::RE.mask());
+            usartx.cr1().set(usart::UE);
         }
     }
 
+    // the nrf51 has a single uart, its baudrate register takes
+    // precalculated values instead of a divisor
+    #[cfg(feature = "microbit")]
+    impl UsartX {
+        pub fn enable(&self) {
+            uart0::BAUDRATE.write(match self.baudrate {
+                115_200 => uart0::BAUD115200,
+                _ => uart0::BAUD9600,
+            });
+            uart0::ENABLE.write(uart0::ENABLED);
+            uart0::STARTTX.write(1);
+        }
+    }
+
+    // blocks until the byte left the transmit register
+    #[cfg(all(feature = "microbit", not(feature = "semihosting")))]
+    fn put(c: u8) {
+        uart0::TXD.write(c as u32);
+        while uart0::TXDRDY.read() == 0 {}
+        uart0::TXDRDY.write(0);
+    }
+
+    // blocks until the transmit register accepts the next byte
+    #[cfg(not(any(feature = "microbit", feature = "semihosting")))]
+    fn put(c: u8) {
+        usart::USART1.tdr().write(c as u32);
+        while !usart::USART1.isr().is_set(usart::TXE) {}
+    }
+
     // propably the world's worst and slowest function to print stupid integers on
     // a screen
     pub fn print_dec(mut dec: u32) {
-        let usart2_tdr = adresses::USART1_BASEADRESS | offsets::usart1::TDR;
-        let usart2_isr = adresses::USART1_BASEADRESS | offsets::usart1::ISR;
         let mut buffer: [u8; 32] = unsafe { core::mem::zeroed() };
         let mut cnt: u8 = 0;
-        while dec > 0 {
+        loop {
             buffer[cnt as usize] = (dec % 10 + 0x30) as u8;
             dec /= 10;
             cnt += 1;
-        }
-        for c in IntoIterator::into_iter(buffer).rev() {
-            unsafe {
-                ptr::write_volatile(usart2_tdr as *mut u32, c as u32);
-                while !((ptr::read_volatile(usart2_isr as *mut u32) & 0x80) != 0) {}
+            if dec == 0 {
+                break;
             }
         }
+        buffer[..cnt as usize].reverse();
+        write_bytes(&buffer[..cnt as usize]);
     }
 
-    pub fn print_from_ptr(mut ptr_start: *mut u8) {
-        let usart2_tdr = adresses::USART1_BASEADRESS | offsets::usart1::TDR;
-        let usart2_isr = adresses::USART1_BASEADRESS | offsets::usart1::ISR;
+    pub fn print_hex(hex: u32) {
+        let mut buffer: [u8; 10] = [b'0', b'x', 0, 0, 0, 0, 0, 0, 0, 0];
+        for nibble in 0..8 {
+            let digit = ((hex >> ((7 - nibble) * 4)) & 0xF) as u8;
+            buffer[2 + nibble] = if digit < 10 { b'0' + digit } else { b'A' + digit - 10 };
+        }
+        write_bytes(&buffer);
+    }
 
-        unsafe {
-            while *ptr_start != b'\0' {
-                write(usart2_tdr, *ptr_start as u32);
-                ptr_start = ptr_start.add(1);
-                while !((read(usart2_isr) & 0x80) != 0) {}
-            }
+    ///
+    /// Writes the nul terminated string at `ptr_start`.
+    /// # Safety
+    /// Every byte up to the nul has to be readable, the kernel
+    /// checks the string of a task before, see `handle_trap`.
+    ///
+    #[cfg(not(feature = "semihosting"))]
+    pub unsafe fn print_from_ptr(mut ptr_start: *mut u8) {
+        while *ptr_start != b'\0' {
+            put(*ptr_start);
+            ptr_start = ptr_start.add(1);
+        }
+    }
+
+    ///
+    /// The debugger reads the string up to the terminating nul itself.
+    /// # Safety
+    /// See the version without semihosting.
+    ///
+    #[cfg(feature = "semihosting")]
+    pub unsafe fn print_from_ptr(ptr_start: *mut u8) {
+        super::semihosting::write0(ptr_start);
+    }
+
+    // the kernel's stdout is usart1 or, with the feature
+    // "semihosting", the console of the debugger
+    #[cfg(not(feature = "semihosting"))]
+    fn write_bytes(bytes: &[u8]) {
+        for c in bytes {
+            put(*c);
+        }
+    }
+
+    #[cfg(feature = "semihosting")]
+    fn write_bytes(bytes: &[u8]) {
+        super::semihosting::write(bytes);
+    }
+
+    ///
+    /// Polled writer for formatted output, e.g. `write!(Writer, "{}", x)`.
+    /// Does not depend on interrupts, so it also works within
+    /// fault handlers and the panic handler.
+    ///
+    pub struct Writer;
+
+    impl core::fmt::Write for Writer {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            print_str(s);
+            Ok(())
         }
     }
 
     pub fn print_str(msg: &str) {
-        let usart2_tdr = adresses::USART1_BASEADRESS | offsets::usart1::TDR;
-        let usart2_isr = adresses::USART1_BASEADRESS | offsets::usart1::ISR;
+        write_bytes(msg.as_bytes());
+    }
+}
+
+///
+/// Requests to the host of a debugger or emulator, which executes
+/// them on behalf of the target. Without a host attached the
+/// requests raise a hardfault, so only builds for an emulator or
+/// with the feature "semihosting" use them.
+///
+pub mod semihosting {
+    use core::sync::atomic::{AtomicU32, Ordering};
 
-        for c in msg.chars() {
-            write(usart2_tdr, c as u32);
-            while !((read(usart2_isr) & 0x80) != 0) {}
+    extern "C" {
+        fn __semihosting_call(operation: u32, parameter: u32) -> u32;
+    }
+
+    // operations, see the semihosting specification of arm
+    const SYS_OPEN: u32 = 0x01;
+    const SYS_WRITE0: u32 = 0x04;
+    const SYS_WRITE: u32 = 0x05;
+    const SYS_EXIT: u32 = 0x18;
+    const SYS_EXIT_EXTENDED: u32 = 0x20;
+
+    const ADP_STOPPED_APPLICATION_EXIT: u32 = 0x2_0026;
+    // mode "w" of fopen
+    const OPEN_MODE_WRITE: u32 = 4;
+    const NO_HANDLE: u32 = u32::MAX;
+
+    // handle of the console, opened on first use
+    static CONSOLE: AtomicU32 = AtomicU32::new(NO_HANDLE);
+
+    fn console() -> u32 {
+        let handle = CONSOLE.load(Ordering::Relaxed);
+        if handle != NO_HANDLE {
+            return handle;
+        }
+        // the special file name ":tt" stands for the console
+        let name = b":tt\0";
+        let block: [u32; 3] = [name.as_ptr() as u32, OPEN_MODE_WRITE, 3];
+        let handle = unsafe { __semihosting_call(SYS_OPEN, block.as_ptr() as u32) };
+        CONSOLE.store(handle, Ordering::Relaxed);
+        handle
+    }
+
+    ///
+    /// Writes `bytes` to the console of the debugger.
+    ///
+    pub fn write(bytes: &[u8]) {
+        let block: [u32; 3] = [console(), bytes.as_ptr() as u32, bytes.len() as u32];
+        unsafe {
+            __semihosting_call(SYS_WRITE, block.as_ptr() as u32);
+        }
+    }
+
+    ///
+    /// Writes the nul terminated string at `start` to the console.
+    ///
+    pub fn write0(start: *const u8) {
+        unsafe {
+            __semihosting_call(SYS_WRITE0, start as u32);
+        }
+    }
+
+    ///
+    /// Stops the emulator, which exits with `code` as its status.
+    /// Plain SYS_EXIT only knows success and failure, so other codes
+    /// need the extended variant.
+    ///
+    pub fn exit(code: u32) {
+        let block: [u32; 2] = [ADP_STOPPED_APPLICATION_EXIT, code];
+        unsafe {
+            match code {
+                0 => __semihosting_call(SYS_EXIT, ADP_STOPPED_APPLICATION_EXIT),
+                _ => __semihosting_call(SYS_EXIT_EXTENDED, block.as_ptr() as u32),
+            };
         }
     }
 }
@@ -196,152 +1178,761 @@ pub mod gpio_types {
         High,
         Low,
     }
+
+    pub enum InputState {
+        High,
+        Low,
+    }
+
+    pub enum PullTypes {
+        NoPull,
+        PullUp,
+        PullDown,
+    }
+
+    // the f4 knows a fourth, very high speed
+    pub enum SpeedTypes {
+        Low,
+        Medium,
+        High,
+    }
 }
 
+#[cfg(not(feature = "microbit"))]
 pub mod gpio_driver {
     use super::gpio_types;
-    use core::ptr;
-
-    // p52
-    const GPIOA_BASE: u32 = 0x4800_0000;
-    const GPIOB_BASE: u32 = 0x4800_0400;
-    const GPIOC_BASE: u32 = 0x4800_0800;
-    const GPIOE_BASE: u32 = 0x4800_1000;
+    use super::platform::adresses;
+    use super::platform::registers::gpio::{self, Gpio};
 
     pub struct GpioX {
-        gpio_base_adress: u32,
+        port: Gpio,
         pin_number: u8,
     }
 
     impl GpioX {
-        pub fn new(port_mnemonic: &str, pin_number: u8) -> GpioX {
+        ///
+        /// Pin `pin_number` of port "A" to "F". None for any other
+        /// port and for pins beyond 15.
+        ///
+        pub fn new(port_mnemonic: &str, pin_number: u8) -> Option<GpioX> {
+            let base = match port_mnemonic {
+                "A" => adresses::GPIOA,
+                "B" => adresses::GPIOB,
+                "C" => adresses::GPIOC,
+                "D" => adresses::GPIOD,
+                "E" => adresses::GPIOE,
+                "F" => adresses::GPIOF,
+                _ => return None,
+            };
+            match pin_number {
+                0..=15 => Some(GpioX {
+                    port: Gpio::at(base),
+                    pin_number,
+                }),
+                _ => None,
+            }
+        }
+        // no checks, for ports and pins known to exist
+        pub(crate) const fn at(base: u32, pin_number: u8) -> GpioX {
             GpioX {
-                gpio_base_adress: match port_mnemonic {
-                    "A" => GPIOA_BASE,
-                    "B" => GPIOB_BASE,
-                    "C" => GPIOC_BASE,
-                    "E" => GPIOE_BASE,
-                    _ => GPIOA_BASE,
-                },
+                port: Gpio::at(base),
                 pin_number,
             }
         }
+        // p237 -> 11.4.1 GPIO port mode register
         pub fn set_moder(&self, moder_type: gpio_types::ModerTypes) {
-            let gpiox_moder_offset = 0x00;
-            let gpiox_moder = self.gpio_base_adress | gpiox_moder_offset;
+            let mode = match moder_type {
+                gpio_types::ModerTypes::InputMode => 0b00,
+                gpio_types::ModerTypes::GeneralPurposeOutputMode => 0b01,
+                gpio_types::ModerTypes::AlternateFunctionMode => 0b10,
+                gpio_types::ModerTypes::AnalogMode => 0b11,
+            };
+            self.port.moder().write_field(gpio::mode(self.pin_number), mode);
+        }
+        // 11.4.6 GPIO port output data register (GPIOx_ODR) (x = A..H)
+        pub fn set_odr(&self, odr_type: gpio_types::OutputState) {
+            match odr_type {
+                gpio_types::OutputState::High => self.set(),
+                gpio_types::OutputState::Low => self.reset(),
+            }
+        }
+        // p237 -> 11.4.2 GPIO port output type register
+        pub fn set_otyper(&self, output_type: gpio_types::OutputTypes) {
+            match output_type {
+                gpio_types::OutputTypes::PushPull => self.port.otyper().clear(gpio::pin(self.pin_number)),
+                gpio_types::OutputTypes::OpenDrain => self.port.otyper().set(gpio::pin(self.pin_number)),
+            }
+        }
+        // 11.4.3 GPIO port output speed register
+        pub fn set_ospeedr(&self, speed_type: gpio_types::SpeedTypes) {
+            let speed = match speed_type {
+                gpio_types::SpeedTypes::Low => 0b00,
+                gpio_types::SpeedTypes::Medium => 0b01,
+                gpio_types::SpeedTypes::High => 0b11,
+            };
+            self.port.ospeedr().write_field(gpio::mode(self.pin_number), speed);
+        }
+        // 11.4.4 GPIO port pull-up/pull-down register
+        pub fn set_pupdr(&self, pull_type: gpio_types::PullTypes) {
+            let pull = match pull_type {
+                gpio_types::PullTypes::NoPull => 0b00,
+                gpio_types::PullTypes::PullUp => 0b01,
+                gpio_types::PullTypes::PullDown => 0b10,
+            };
+            self.port.pupdr().write_field(gpio::mode(self.pin_number), pull);
+        }
+        // 11.4.5 GPIO port input data register
+        pub fn read_idr(&self) -> gpio_types::InputState {
+            match self.port.idr().is_set(gpio::pin(self.pin_number)) {
+                true => gpio_types::InputState::High,
+                false => gpio_types::InputState::Low,
+            }
+        }
+        ///
+        /// Drives the pin high. The write to bsrr leaves the other pins
+        /// alone, so no interrupt can undo a concurrent change of them.
+        ///
+        pub fn set(&self) {
+            self.port.bsrr().write(gpio::set(self.pin_number).mask());
+        }
+        ///
+        /// Drives the pin low, see `set`.
+        ///
+        pub fn reset(&self) {
+            self.port.bsrr().write(gpio::reset(self.pin_number).mask());
+        }
+        pub fn toggle(&self) {
+            match self.port.odr().is_set(gpio::pin(self.pin_number)) {
+                true => self.reset(),
+                false => self.set(),
+            }
+        }
+        pub fn into_af(&self, af_number: u32) {
+            self.port.afr(self.pin_number).write_field(gpio::af(self.pin_number), af_number);
+        }
+    }
+}
 
-            // 32 bit register
-            let mut current_register_content: u32;
+///
+/// Pins with their mode in the type, on top of `gpio_driver`. Every pin
+/// has a type of its own (`gpioe::PE11`) and can be taken only once,
+/// so no two drivers configure the same pin. A pin starts as floating
+/// input, the `into_*` functions change its mode:
+///
+/// let led = gpioe::PE11::take()?.into_output::<PushPull>();
+/// led.set_high();
+///
+#[cfg(not(feature = "microbit"))]
+pub mod gpio {
+    use super::gpio_driver::GpioX;
+    use super::gpio_types::{InputState, ModerTypes, OutputTypes, PullTypes, SpeedTypes};
+    use super::platform::adresses;
+    use crate::sched::critical;
+    use core::marker::PhantomData;
+    use core::sync::atomic::{AtomicU32, Ordering};
 
-            unsafe {
-                current_register_content = ptr::read_volatile(gpiox_moder as *const u32);
-            }
-            // clear bits
-            // current_register_content &= !(0b11 as u32) << pin * 2;
+    // pins in use, port a in bits 0 - 15 of the first word, port b
+    // in bits 16 - 31 and so on
+    static TAKEN: [AtomicU32; 3] = [AtomicU32::new(0), AtomicU32::new(0), AtomicU32::new(0)];
 
-            let updated_register_content = match moder_type {
-                // clear bit
-                gpio_types::ModerTypes::InputMode => {
-                    current_register_content |= (0b00 as u32) << (self.pin_number * 2);
-                    current_register_content
-                }
-                // set bit
-                gpio_types::ModerTypes::GeneralPurposeOutputMode => {
-                    current_register_content |= (0b01 as u32) << (self.pin_number * 2);
-                    current_register_content
-                }
-                gpio_types::ModerTypes::AlternateFunctionMode => {
-                    current_register_content |= (0b10 as u32) << self.pin_number * 2;
-                    current_register_content
-                }
-                gpio_types::ModerTypes::AnalogMode => {
-                    current_register_content |= (0b11 as u32) << self.pin_number * 2;
-                    current_register_content
+    pub struct Input<PULL> {
+        pull: PhantomData<PULL>,
+    }
+    pub struct Output<OTYPE> {
+        otype: PhantomData<OTYPE>,
+    }
+    pub struct Alternate<AF> {
+        af: PhantomData<AF>,
+    }
+    pub struct Analog;
+
+    pub struct Floating;
+    pub struct PullUp;
+    pub struct PullDown;
+
+    pub struct PushPull;
+    pub struct OpenDrain;
+
+    ///
+    /// Mode of a pin as written to moder.
+    ///
+    pub trait Mode {
+        const MODER: ModerTypes;
+    }
+    pub trait Pull {
+        const PULL: PullTypes;
+    }
+    pub trait OutputType {
+        const OTYPE: OutputTypes;
+    }
+    pub trait AlternateFunction {
+        const AF: u32;
+    }
+    ///
+    /// Port and number of a pin, implemented by the pins of the port modules.
+    ///
+    pub trait PinId {
+        // 0 for port a, 1 for port b and so on
+        const PORT: u32;
+        const BASE: u32;
+        const PIN: u8;
+    }
+
+    impl<PULL> Mode for Input<PULL> {
+        const MODER: ModerTypes = ModerTypes::InputMode;
+    }
+    impl<OTYPE> Mode for Output<OTYPE> {
+        const MODER: ModerTypes = ModerTypes::GeneralPurposeOutputMode;
+    }
+    impl<AF> Mode for Alternate<AF> {
+        const MODER: ModerTypes = ModerTypes::AlternateFunctionMode;
+    }
+    impl Mode for Analog {
+        const MODER: ModerTypes = ModerTypes::AnalogMode;
+    }
+
+    impl Pull for Floating {
+        const PULL: PullTypes = PullTypes::NoPull;
+    }
+    impl Pull for PullUp {
+        const PULL: PullTypes = PullTypes::PullUp;
+    }
+    impl Pull for PullDown {
+        const PULL: PullTypes = PullTypes::PullDown;
+    }
+
+    impl OutputType for PushPull {
+        const OTYPE: OutputTypes = OutputTypes::PushPull;
+    }
+    impl OutputType for OpenDrain {
+        const OTYPE: OutputTypes = OutputTypes::OpenDrain;
+    }
+
+    macro_rules! alternate_functions {
+        ($($AFi:ident: $i:expr),+) => {
+            $(
+                pub struct $AFi;
+                impl AlternateFunction for $AFi {
+                    const AF: u32 = $i;
                 }
-            };
-            unsafe {
-                ptr::write_volatile(gpiox_moder as *mut u32, updated_register_content);
+            )+
+        };
+    }
+
+    alternate_functions!(
+        AF0: 0, AF1: 1, AF2: 2, AF3: 3, AF4: 4, AF5: 5, AF6: 6, AF7: 7,
+        AF8: 8, AF9: 9, AF10: 10, AF11: 11, AF12: 12, AF13: 13, AF14: 14, AF15: 15
+    );
+
+    pub struct Pin<ID, MODE> {
+        id: PhantomData<ID>,
+        mode: PhantomData<MODE>,
+    }
+
+    impl<ID: PinId> Pin<ID, Input<Floating>> {
+        ///
+        /// The pin in its reset state, None if it is taken already.
+        ///
+        pub fn take() -> Option<Self> {
+            match reserve(ID::PORT, ID::PIN) {
+                true => Some(Pin::new()),
+                false => None,
             }
         }
-        // 11.4.6 GPIO port output data register (GPIOx_ODR) (x = A..H)
-        pub fn set_odr(&self, odr_type: gpio_types::OutputState) {
-            let gpiox_odr_offset = 0x14;
-            let gpiox_odr = self.gpio_base_adress | gpiox_odr_offset;
+    }
 
-            // 32 bit register
-            let mut current_register_content: u32;
+    impl<ID: PinId, MODE: Mode> Pin<ID, MODE> {
+        const fn new() -> Self {
+            Pin {
+                id: PhantomData,
+                mode: PhantomData,
+            }
+        }
 
-            unsafe {
-                current_register_content = ptr::read_volatile(gpiox_odr as *const u32);
+        fn driver() -> GpioX {
+            GpioX::at(ID::BASE, ID::PIN)
+        }
+
+        pub fn into_input<PULL: Pull>(self) -> Pin<ID, Input<PULL>> {
+            let pin = Self::driver();
+            pin.set_moder(ModerTypes::InputMode);
+            pin.set_pupdr(PULL::PULL);
+            Pin::new()
+        }
+
+        pub fn into_output<OTYPE: OutputType>(self) -> Pin<ID, Output<OTYPE>> {
+            let pin = Self::driver();
+            pin.set_otyper(OTYPE::OTYPE);
+            pin.set_pupdr(PullTypes::NoPull);
+            pin.set_moder(ModerTypes::GeneralPurposeOutputMode);
+            Pin::new()
+        }
+
+        ///
+        /// Hands the pin to a peripheral. The function gets selected
+        /// before the mode, so the pin never drives another one.
+        ///
+        pub fn into_alternate<AF: AlternateFunction>(self) -> Pin<ID, Alternate<AF>> {
+            let pin = Self::driver();
+            pin.into_af(AF::AF);
+            pin.set_moder(ModerTypes::AlternateFunctionMode);
+            Pin::new()
+        }
+
+        pub fn into_analog(self) -> Pin<ID, Analog> {
+            let pin = Self::driver();
+            pin.set_pupdr(PullTypes::NoPull);
+            pin.set_moder(ModerTypes::AnalogMode);
+            Pin::new()
+        }
+
+        ///
+        /// Gives up the pin, its mode stays as it is.
+        /// It can be taken again afterwards.
+        ///
+        pub fn release(self) {
+            unreserve(ID::PORT, ID::PIN);
+        }
+    }
+
+    impl<ID: PinId, PULL> Pin<ID, Input<PULL>> {
+        pub fn is_high(&self) -> bool {
+            matches!(GpioX::at(ID::BASE, ID::PIN).read_idr(), InputState::High)
+        }
+
+        pub fn is_low(&self) -> bool {
+            !self.is_high()
+        }
+    }
+
+    impl<ID: PinId, OTYPE> Pin<ID, Output<OTYPE>> {
+        pub fn set_high(&self) {
+            GpioX::at(ID::BASE, ID::PIN).set();
+        }
+
+        pub fn set_low(&self) {
+            GpioX::at(ID::BASE, ID::PIN).reset();
+        }
+
+        pub fn toggle(&self) {
+            GpioX::at(ID::BASE, ID::PIN).toggle();
+        }
+
+        pub fn set_speed(&self, speed: SpeedTypes) {
+            GpioX::at(ID::BASE, ID::PIN).set_ospeedr(speed);
+        }
+    }
+
+    impl<ID: PinId, AF> Pin<ID, Alternate<AF>> {
+        pub fn set_speed(&self, speed: SpeedTypes) {
+            GpioX::at(ID::BASE, ID::PIN).set_ospeedr(speed);
+        }
+    }
+
+    ///
+    /// Marks a pin as taken. False if it was taken already.
+    /// The board reserves the pins it configures on its own.
+    ///
+    // armv6m has no atomic read-modify-write, so the
+    // load and the store happen within a critical section
+    pub(crate) fn reserve(port: u32, pin: u8) -> bool {
+        let (word, bit) = position(port, pin);
+        critical::section(|| {
+            let taken = TAKEN[word].load(Ordering::Relaxed);
+            if taken & bit != 0 {
+                return false;
             }
-            // clear bits
-            // current_register_content &= !(0b1 as u32) << self.pin_number;
+            TAKEN[word].store(taken | bit, Ordering::Relaxed);
+            true
+        })
+    }
 
-            let updated_register_content = match odr_type {
-                gpio_types::OutputState::High => {
-                    current_register_content |= (0b1 as u32) << self.pin_number;
-                    current_register_content
-                }
-                gpio_types::OutputState::Low => {
-                    // todo: does not work
-                    current_register_content ^= !(0b0 as u32) << self.pin_number;
-                    current_register_content
-                }
-            };
-            unsafe {
-                ptr::write_volatile(gpiox_odr as *mut u32, updated_register_content);
+    pub(crate) fn is_taken(port: u32, pin: u8) -> bool {
+        let (word, bit) = position(port, pin);
+        TAKEN[word].load(Ordering::Relaxed) & bit != 0
+    }
+
+    pub(crate) fn unreserve(port: u32, pin: u8) {
+        let (word, bit) = position(port, pin);
+        critical::section(|| {
+            TAKEN[word].store(TAKEN[word].load(Ordering::Relaxed) & !bit, Ordering::Relaxed);
+        })
+    }
+
+    ///
+    /// Reserves a pin by its name ("A" to "F") for a driver and switches
+    /// it to the alternate function `af`, e.g. a timer channel. None if
+    /// the pin does not exist or is taken already.
+    ///
+    pub(crate) fn take_alternate(port: &str, pin: u8, af: u32) -> Option<GpioX> {
+        let driver = GpioX::new(port, pin)?;
+        if !reserve(port.as_bytes()[0] as u32 - b'A' as u32, pin) {
+            return None;
+        }
+        driver.into_af(af);
+        driver.set_moder(ModerTypes::AlternateFunctionMode);
+        Some(driver)
+    }
+
+    fn position(port: u32, pin: u8) -> (usize, u32) {
+        ((port / 2) as usize, 0b1 << ((port % 2) * 16 + pin as u32))
+    }
+
+    macro_rules! port {
+        ($port:ident, $index:expr, $base:expr, [$($PXi:ident, $Id:ident: $i:expr),+]) => {
+            pub mod $port {
+                use super::{adresses, Floating, Input, Pin, PinId};
+
+                $(
+                    pub struct $Id;
+                    impl PinId for $Id {
+                        const PORT: u32 = $index;
+                        const BASE: u32 = $base;
+                        const PIN: u8 = $i;
+                    }
+                    pub type $PXi<MODE = Input<Floating>> = Pin<$Id, MODE>;
+                )+
             }
+        };
+    }
+
+    port!(gpioa, 0, adresses::GPIOA, [
+        PA0, Pa0: 0, PA1, Pa1: 1, PA2, Pa2: 2, PA3, Pa3: 3, PA4, Pa4: 4, PA5, Pa5: 5, PA6, Pa6: 6, PA7, Pa7: 7,
+        PA8, Pa8: 8, PA9, Pa9: 9, PA10, Pa10: 10, PA11, Pa11: 11, PA12, Pa12: 12, PA13, Pa13: 13, PA14, Pa14: 14, PA15, Pa15: 15
+    ]);
+    port!(gpiob, 1, adresses::GPIOB, [
+        PB0, Pb0: 0, PB1, Pb1: 1, PB2, Pb2: 2, PB3, Pb3: 3, PB4, Pb4: 4, PB5, Pb5: 5, PB6, Pb6: 6, PB7, Pb7: 7,
+        PB8, Pb8: 8, PB9, Pb9: 9, PB10, Pb10: 10, PB11, Pb11: 11, PB12, Pb12: 12, PB13, Pb13: 13, PB14, Pb14: 14, PB15, Pb15: 15
+    ]);
+    port!(gpioc, 2, adresses::GPIOC, [
+        PC0, Pc0: 0, PC1, Pc1: 1, PC2, Pc2: 2, PC3, Pc3: 3, PC4, Pc4: 4, PC5, Pc5: 5, PC6, Pc6: 6, PC7, Pc7: 7,
+        PC8, Pc8: 8, PC9, Pc9: 9, PC10, Pc10: 10, PC11, Pc11: 11, PC12, Pc12: 12, PC13, Pc13: 13, PC14, Pc14: 14, PC15, Pc15: 15
+    ]);
+    port!(gpiod, 3, adresses::GPIOD, [
+        PD0, Pd0: 0, PD1, Pd1: 1, PD2, Pd2: 2, PD3, Pd3: 3, PD4, Pd4: 4, PD5, Pd5: 5, PD6, Pd6: 6, PD7, Pd7: 7,
+        PD8, Pd8: 8, PD9, Pd9: 9, PD10, Pd10: 10, PD11, Pd11: 11, PD12, Pd12: 12, PD13, Pd13: 13, PD14, Pd14: 14, PD15, Pd15: 15
+    ]);
+    port!(gpioe, 4, adresses::GPIOE, [
+        PE0, Pe0: 0, PE1, Pe1: 1, PE2, Pe2: 2, PE3, Pe3: 3, PE4, Pe4: 4, PE5, Pe5: 5, PE6, Pe6: 6, PE7, Pe7: 7,
+        PE8, Pe8: 8, PE9, Pe9: 9, PE10, Pe10: 10, PE11, Pe11: 11, PE12, Pe12: 12, PE13, Pe13: 13, PE14, Pe14: 14, PE15, Pe15: 15
+    ]);
+    port!(gpiof, 5, adresses::GPIOF, [
+        PF0, Pf0: 0, PF1, Pf1: 1, PF2, Pf2: 2, PF3, Pf3: 3, PF4, Pf4: 4, PF5, Pf5: 5, PF6, Pf6: 6, PF7, Pf7: 7,
+        PF8, Pf8: 8, PF9, Pf9: 9, PF10, Pf10: 10, PF11, Pf11: 11, PF12, Pf12: 12, PF13, Pf13: 13, PF14, Pf14: 14, PF15, Pf15: 15
+    ]);
+}
+
+///
+/// Pins claimed by tasks through the gpio syscalls. A pin belongs to
+/// the task which claimed it first until this task terminates, no
+/// other task can reconfigure, drive or read it meanwhile. Claimed
+/// pins are taken for `gpio` as well, so no driver grabs them.
+///
+#[cfg(not(feature = "microbit"))]
+pub mod gpio_owner {
+    use super::gpio;
+    use super::gpio_driver::GpioX;
+    use super::gpio_types::{InputState, ModerTypes, OutputTypes, PullTypes};
+    use crate::sched::critical;
+    use core::ptr::addr_of_mut;
+
+    // modes of `claim`
+    pub const INPUT: u32 = 0;
+    pub const INPUT_PULLUP: u32 = 1;
+    pub const INPUT_PULLDOWN: u32 = 2;
+    pub const OUTPUT: u32 = 3;
+    pub const OUTPUT_OPEN_DRAIN: u32 = 4;
+
+    const PORTS: usize = 6;
+    const PINS: usize = 16;
+    const NO_OWNER: u32 = u32::MAX;
+
+    static mut OWNERS: [[u32; PINS]; PORTS] = [[NO_OWNER; PINS]; PORTS];
+
+    fn owners<'a>() -> &'a mut [[u32; PINS]; PORTS] {
+        unsafe { &mut *addr_of_mut!(OWNERS) }
+    }
+
+    // port 'A' to 'F' and pin 0 - 15 as indices
+    fn locate(port: u32, pin: u32) -> Option<(usize, usize)> {
+        match (port, pin) {
+            (0x41..=0x46, 0..=15) => Some(((port - 0x41) as usize, pin as usize)),
+            _ => None,
         }
-        // p237 -> 11.4.2 GPIO port output type register
-        pub fn set_otyper(&self, output_type: gpio_types::OutputTypes) {
-            let gpiox_otyper_offset = 0x04;
-            let gpiox_otyper = self.gpio_base_adress | gpiox_otyper_offset;
+    }
+
+    // the driver of a pin owned by `pid`
+    fn owned(port: u32, pin: u32, pid: u32) -> Option<GpioX> {
+        let (p, n) = locate(port, pin)?;
+        if owners()[p][n] != pid {
+            return None;
+        }
+        let mnemonic = [port as u8];
+        GpioX::new(core::str::from_utf8(&mnemonic).ok()?, n as u8)
+    }
 
-            // 32 bit register
-            let mut current_register_content: u32;
+    ///
+    /// Hands the pin to the task `pid` and configures it. A task may
+    /// claim its own pins again to change their mode. Returns false
+    /// if the pin or the mode does not exist or the pin is in use.
+    ///
+    pub fn claim(port: u32, pin: u32, mode: u32, pid: u32) -> bool {
+        let (p, n) = match locate(port, pin) {
+            Some(l) => l,
+            None => return false,
+        };
+        if mode > OUTPUT_OPEN_DRAIN {
+            return false;
+        }
+        let claimed = critical::section(|| {
+            let owner = owners()[p][n];
+            if owner != pid && (owner != NO_OWNER || !gpio::reserve(p as u32, n as u8)) {
+                return false;
+            }
+            owners()[p][n] = pid;
+            true
+        });
+        if !claimed {
+            return false;
+        }
 
-            unsafe {
-                current_register_content = ptr::read_volatile(gpiox_otyper as *const u32);
+        let driver = match owned(port, pin, pid) {
+            Some(d) => d,
+            None => return false,
+        };
+        match mode {
+            OUTPUT | OUTPUT_OPEN_DRAIN => {
+                driver.set_otyper(match mode {
+                    OUTPUT => OutputTypes::PushPull,
+                    _ => OutputTypes::OpenDrain,
+                });
+                driver.set_pupdr(PullTypes::NoPull);
+                driver.set_moder(ModerTypes::GeneralPurposeOutputMode);
             }
+            _ => {
+                driver.set_moder(ModerTypes::InputMode);
+                driver.set_pupdr(match mode {
+                    INPUT_PULLUP => PullTypes::PullUp,
+                    INPUT_PULLDOWN => PullTypes::PullDown,
+                    _ => PullTypes::NoPull,
+                });
+            }
+        }
+        true
+    }
 
-            let updated_register_content = match output_type {
-                // clear bit
-                gpio_types::OutputTypes::PushPull => {
-                    current_register_content &= !(1 as u32) << self.pin_number;
-                    current_register_content
+    pub fn write(port: u32, pin: u32, high: bool, pid: u32) -> bool {
+        match owned(port, pin, pid) {
+            Some(d) => {
+                match high {
+                    true => d.set(),
+                    false => d.reset(),
                 }
-                // set bit
-                gpio_types::OutputTypes::OpenDrain => {
-                    current_register_content |= (1 as u32) << self.pin_number;
-                    current_register_content
-                }
-            };
-            unsafe {
-                ptr::write_volatile(gpiox_otyper as *mut u32, updated_register_content);
+                true
             }
+            None => false,
         }
-        pub fn into_af(&self, af_number: u32) {
-            let gpiox_af_offset = if self.pin_number < 8 { 0x20 } else { 0x24 };
-            let gpiox_af = self.gpio_base_adress | gpiox_af_offset;
+    }
+
+    pub fn toggle(port: u32, pin: u32, pid: u32) -> bool {
+        match owned(port, pin, pid) {
+            Some(d) => {
+                d.toggle();
+                true
+            }
+            None => false,
+        }
+    }
 
-            // 32 bit register
-            let mut current_register_content: u32;
+    ///
+    /// Whether `pid` may use the pin without claiming it, e.g. as
+    /// interrupt source. True if it owns the pin or nobody took it.
+    ///
+    pub fn usable(port: u32, pin: u32, pid: u32) -> bool {
+        match locate(port, pin) {
+            Some((p, n)) => match owners()[p][n] {
+                NO_OWNER => !gpio::is_taken(p as u32, n as u8),
+                owner => owner == pid,
+            },
+            None => false,
+        }
+    }
 
-            unsafe {
-                current_register_content = ptr::read_volatile(gpiox_af as *const u32);
+    ///
+    /// Level of the pin, None if `pid` does not own it.
+    ///
+    pub fn read(port: u32, pin: u32, pid: u32) -> Option<bool> {
+        let driver = owned(port, pin, pid)?;
+        Some(matches!(driver.read_idr(), InputState::High))
+    }
+
+    ///
+    /// Frees every pin of a terminated task, they keep their mode.
+    ///
+    pub fn release_all(pid: u32) {
+        critical::section(|| {
+            for (p, port) in owners().iter_mut().enumerate() {
+                for (n, owner) in port.iter_mut().enumerate() {
+                    if *owner == pid {
+                        *owner = NO_OWNER;
+                        gpio::unreserve(p as u32, n as u8);
+                    }
+                }
             }
+        })
+    }
+}
+
+// the gpio of the nrf51 is not available to tasks
+#[cfg(feature = "microbit")]
+pub mod gpio_owner {
+    pub fn claim(_port: u32, _pin: u32, _mode: u32, _pid: u32) -> bool {
+        false
+    }
+
+    pub fn write(_port: u32, _pin: u32, _high: bool, _pid: u32) -> bool {
+        false
+    }
+
+    pub fn toggle(_port: u32, _pin: u32, _pid: u32) -> bool {
+        false
+    }
+
+    pub fn read(_port: u32, _pin: u32, _pid: u32) -> Option<bool> {
+        None
+    }
+
+    pub fn usable(_port: u32, _pin: u32, _pid: u32) -> bool {
+        false
+    }
+
+    pub fn release_all(_pid: u32) {}
+}
 
-            let mut pin = self.pin_number;
-            if self.pin_number > 7 {
-                pin -= 8;
+///
+/// External interrupts of the gpio pins. Line n serves pin n of one
+/// port at a time, which port gets selected in syscfg. Tasks wait
+/// for the edges of a line through an event flag, see `arm`.
+///
+#[cfg(not(feature = "microbit"))]
+pub mod exti {
+    use super::gpio_owner;
+    use super::platform::irqn;
+    use super::platform::registers::{exti, syscfg};
+    use crate::interrupts::irq;
+    use crate::sched::{event, task_control};
+    use core::ptr::addr_of_mut;
+
+    // edges a line reacts on, may be combined
+    pub const RISING: u32 = 0b01;
+    pub const FALLING: u32 = 0b10;
+
+    const LINES: usize = 16;
+
+    struct Line {
+        port: u32,
+        // event flag the waiting tasks block on
+        event: u32,
+        // edges within this many ticks after the last reported one get dropped
+        debounce: u32,
+        // tick of the last reported edge
+        last: Option<u32>,
+    }
+
+    const UNUSED: Option<Line> = None;
+    static mut STATE: [Option<Line>; LINES] = [UNUSED; LINES];
+
+    ///
+    /// Configures the line of `pin` for the given edges of `port`
+    /// ('A' to 'F') and returns the event flag to wait on. The pin
+    /// keeps its mode, after reset every pin is an input.
+    /// Returns None if the pin does not exist, no edge is given, the
+    /// line already serves another port or the task `pid` may not use
+    /// the pin, see gpio_owner::usable.
+    ///
+    pub fn arm(port: u32, pin: u32, edges: u32, debounce: u32, pid: u32) -> Option<u32> {
+        if !gpio_owner::usable(port, pin, pid) {
+            return None;
+        }
+        let port = match port {
+            0x41..=0x46 => port - 0x41,
+            _ => return None,
+        };
+        if pin as usize >= LINES || edges & (RISING | FALLING) == 0 {
+            return None;
+        }
+        let line = pin as u8;
+        let state = unsafe { &mut (*addr_of_mut!(STATE))[line as usize] };
+        match state {
+            Some(l) if l.port != port => return None,
+            Some(l) => l.debounce = debounce,
+            None => {
+                *state = Some(Line {
+                    port,
+                    event: event::allocate()?,
+                    debounce,
+                    last: None,
+                });
+                syscfg::exticr(line).write_field(syscfg::exti_port(line), port);
+                exti::PR.write(exti::line(line).mask());
+                exti::IMR.set(exti::line(line));
+                irq::register_irq(irq_of(line), handle_lines);
             }
+        }
+        match edges & RISING {
+            0 => exti::RTSR.clear(exti::line(line)),
+            _ => exti::RTSR.set(exti::line(line)),
+        }
+        match edges & FALLING {
+            0 => exti::FTSR.clear(exti::line(line)),
+            _ => exti::FTSR.set(exti::line(line)),
+        }
+        state.as_ref().map(|l| l.event)
+    }
+
+    fn irq_of(line: u8) -> usize {
+        match line {
+            0 => irqn::EXTI0,
+            1 => irqn::EXTI1,
+            2 => irqn::EXTI2,
+            3 => irqn::EXTI3,
+            4 => irqn::EXTI4,
+            5..=9 => irqn::EXTI9_5,
+            _ => irqn::EXTI15_10,
+        }
+    }
 
-            current_register_content &= !(0xF as u32) << pin * 4;
-            current_register_content |= af_number << pin * 4;
+    // handler of every exti interrupt, the shared ones serve several lines
+    fn handle_lines() {
+        let pending = exti::PR.read() & exti::IMR.read();
+        exti::PR.write(pending);
+        let now = task_control::ticks();
 
-            unsafe {
-                ptr::write_volatile(gpiox_af as *mut u32, current_register_content);
+        for line in 0..LINES {
+            if pending & (0b1 << line) == 0 {
+                continue;
+            }
+            if let Some(l) = unsafe { (*addr_of_mut!(STATE))[line].as_mut() } {
+                let bouncing = match l.last {
+                    Some(last) => now.wrapping_sub(last) < l.debounce,
+                    None => false,
+                };
+                if !bouncing {
+                    l.last = Some(now);
+                    event::signal(l.event);
+                }
             }
         }
     }
 }
+
+// the nrf51 signals pin changes through its gpiote, which is not supported
+#[cfg(feature = "microbit")]
+pub mod exti {
+    pub const RISING: u32 = 0b01;
+    pub const FALLING: u32 = 0b10;
+
+    pub fn arm(_port: u32, _pin: u32, _edges: u32, _debounce: u32, _pid: u32) -> Option<u32> {
+        None
+    }
+}
@@ -0,0 +1,222 @@
+//!
+//! Diagnostics for the fault exceptions and kernel panics. Reads the
+//! fault status registers of the system control block and translates
+//! them into readable causes. Panics leave a crash record which
+//! survives the following reset.
+//!
+use super::cpu;
+#[cfg(not(feature = "armv6m"))]
+pub mod status {
+    use super::cpu::c_registers::scb::{self, cfsr, hfsr};
+    use crate::dev::uart::{print_hex, print_str};
+    use crate::generic::reg::Field;
+
+    const CAUSES: [(Field, &str); 17] = [
+        (cfsr::IACCVIOL, "instruction access violation"),
+        (cfsr::DACCVIOL, "data access violation"),
+        (cfsr::MUNSTKERR, "mpu fault on exception return"),
+        (cfsr::MSTKERR, "stack overflow (mpu fault on exception entry)"),
+        (cfsr::MLSPERR, "mpu fault during lazy fpu stacking"),
+        (cfsr::IBUSERR, "instruction bus error"),
+        (cfsr::PRECISERR, "precise data bus error"),
+        (cfsr::IMPRECISERR, "imprecise data bus error"),
+        (cfsr::UNSTKERR, "bus fault on exception return"),
+        (cfsr::STKERR, "stack overflow (bus fault on exception entry)"),
+        (cfsr::LSPERR, "bus fault during lazy fpu stacking"),
+        (cfsr::UNDEFINSTR, "undefined instruction"),
+        (cfsr::INVSTATE, "invalid state (thumb bit cleared)"),
+        (cfsr::INVPC, "invalid exc_return on pc load"),
+        (cfsr::NOCP, "coprocessor access while disabled"),
+        (cfsr::UNALIGNED, "unaligned access"),
+        (cfsr::DIVBYZERO, "divide by zero"),
+    ];
+
+    ///
+    /// Splits bus-, memmanage- and usagefault from the hardfault
+    /// so they get their own exception and a detailed status.
+    /// Integer divisions by zero trap as well instead of returning 0.
+    ///
+    pub fn enable_fault_handlers() {
+        scb::SHCSR.modify(|r| {
+            r | scb::MEMFAULTENA.mask() | scb::BUSFAULTENA.mask() | scb::USGFAULTENA.mask()
+        });
+        scb::CCR.set(scb::DIV_0_TRP);
+    }
+
+    pub fn name(vector: u32) -> &'static str {
+        match vector {
+            3 => "HARDFAULT",
+            4 => "MEMMANAGE",
+            5 => "BUSFAULT",
+            6 => "USAGEFAULT",
+            _ => "FAULT",
+        }
+    }
+
+    ///
+    /// Prints every cause flagged in cfsr and hfsr together with the
+    /// faulting adresses, if the cpu captured them.
+    ///
+    pub fn print_causes() {
+        for (field, cause) in CAUSES.iter() {
+            if scb::CFSR.is_set(*field) {
+                print_str("  cause: ");
+                print_str(cause);
+                print_str("\n\r");
+            }
+        }
+        if scb::HFSR.is_set(hfsr::VECTTBL) {
+            print_str("  cause: bus fault on vector table read\n\r");
+        }
+        if scb::HFSR.is_set(hfsr::FORCED) {
+            print_str("  cause: escalated to hardfault\n\r");
+        }
+        if scb::CFSR.is_set(cfsr::MMARVALID) {
+            print_str("  mmfar: ");
+            print_hex(scb::MMFAR.read());
+            print_str("\n\r");
+        }
+        if scb::CFSR.is_set(cfsr::BFARVALID) {
+            print_str("  bfar: ");
+            print_hex(scb::BFAR.read());
+            print_str("\n\r");
+        }
+    }
+
+    ///
+    /// False if the cpu failed to push the exception frame.
+    ///
+    pub fn frame_stacked() -> bool {
+        !scb::CFSR.is_set(cfsr::MSTKERR) && !scb::CFSR.is_set(cfsr::STKERR)
+    }
+
+    ///
+    /// Status bits are sticky, writing them back clears them
+    /// so the next fault starts with a clean state.
+    ///
+    pub fn clear() {
+        scb::CFSR.modify(|r| r);
+        scb::HFSR.modify(|r| r);
+    }
+}
+
+// armv6-m knows the hardfault only and has no fault status registers,
+// an access to them would raise a fault on its own
+#[cfg(feature = "armv6m")]
+pub mod status {
+    pub fn enable_fault_handlers() {}
+
+    pub fn name(_vector: u32) -> &'static str {
+        "HARDFAULT"
+    }
+
+    pub fn print_causes() {}
+
+    pub fn frame_stacked() -> bool {
+        true
+    }
+
+    pub fn clear() {}
+}
+
+pub mod crash {
+    use super::cpu::c_registers::scb;
+    use crate::dev::uart::print_str;
+    use core::fmt::{Display, Write};
+    use core::mem::MaybeUninit;
+    use core::ptr::addr_of_mut;
+
+    extern "C" {
+        fn __data_barrier();
+    }
+
+    // marks a valid record, the content of .noinit is random after power on
+    const MAGIC: u32 = 0xDEAD_C0DE;
+    const TEXT_SIZE: usize = 192;
+
+    #[repr(C)]
+    struct CrashRecord {
+        magic: u32,
+        len: u32,
+        text: [u8; TEXT_SIZE],
+    }
+
+    // keeps the formatted output of the panic handler. as the section
+    // is neither zeroed nor initialized by Reset, it survives a soft reset.
+    #[link_section = ".noinit"]
+    static mut RECORD: MaybeUninit<CrashRecord> = MaybeUninit::uninit();
+
+    // fills the record text and cuts off everything beyond its size
+    struct RecordWriter<'a> {
+        record: &'a mut CrashRecord,
+    }
+
+    impl Write for RecordWriter<'_> {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            for b in s.bytes() {
+                let len = self.record.len as usize;
+                if len == TEXT_SIZE {
+                    break;
+                }
+                self.record.text[len] = b;
+                self.record.len += 1;
+            }
+            Ok(())
+        }
+    }
+
+    fn record<'a>() -> &'a mut CrashRecord {
+        unsafe { &mut *(addr_of_mut!(RECORD) as *mut CrashRecord) }
+    }
+
+    ///
+    /// Stores location and message of the panic, everything
+    /// beyond the size of the record gets cut off.
+    ///
+    pub fn save(info: &impl Display) {
+        let record = record();
+        record.magic = MAGIC;
+        record.len = 0;
+        let _ = write!(RecordWriter { record }, "{}", info);
+    }
+
+    ///
+    /// Prints the record of the last run, if there is one,
+    /// and invalidates it afterwards.
+    ///
+    pub fn print_last() {
+        let record = record();
+        if record.magic != MAGIC {
+            return;
+        }
+        let len = (record.len as usize).min(TEXT_SIZE);
+        print_str("---- CRASH RECORD OF LAST RUN ----\n\r");
+        // the text may got cut off within a multibyte character
+        let text = &record.text[..len];
+        let text = match core::str::from_utf8(text) {
+            Ok(t) => t,
+            Err(e) => core::str::from_utf8(&text[..e.valid_up_to()]).unwrap_or(""),
+        };
+        print_str(text);
+        print_str("\n\r----------------------------------\n\r");
+        record.magic = 0;
+    }
+
+    ///
+    /// Requests a system reset through the application interrupt
+    /// and reset control register. The priority grouping is kept.
+    ///
+    pub fn system_reset() -> ! {
+        let prigroup = scb::AIRCR.read() & scb::PRIGROUP.mask();
+        unsafe {
+            // the crash record has to reach the sram before the reset
+            __data_barrier();
+            scb::AIRCR.write(scb::VECTKEY.value(scb::VECTKEY_VALUE) | prigroup | scb::SYSRESETREQ.mask());
+            __data_barrier();
+        }
+        // the reset takes a few cycles, nothing may run meanwhile
+        loop {
+            core::hint::spin_loop();
+        }
+    }
+}
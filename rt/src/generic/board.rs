@@ -0,0 +1,160 @@
+//!
+//! Everything which differs between the boards the kernel runs on.
+//! A board is selected by a cargo feature of `rt`:
+//!
+//! `f303` (default) - stm32f3 discovery, stm32f303vc
+//! `f407`           - stm32f407vet6 development board
+//! `qemu`           - `qemu-system-arm -M netduinoplus2`, stm32f405
+//! `microbit`       - bbc micro:bit, nrf51822 (cortex-m0), runs under
+//!                    `qemu-system-arm -M microbit` as well
+//!
+//! The chip of the board provides the peripheral adresses and the irq
+//! map (see `platform`), `build.rs` the memory layout for the linker.
+//! Scheduler and syscalls do not depend on the board.
+//!
+//! The clock tree does not get configured, every board keeps running
+//! from its clock after reset and the pll stays off. `Board` only
+//! tells the kernel the resulting core and timer clocks.
+//!
+use crate::dev::uart;
+#[cfg(not(feature = "microbit"))]
+use crate::dev::{gpio, gpio_driver::GpioX, gpio_types};
+#[cfg(not(feature = "microbit"))]
+use crate::generic::platform::registers::rcc;
+#[cfg(feature = "microbit")]
+use crate::generic::platform::registers::uart as uart0;
+
+pub struct Board {
+    pub name: &'static str,
+    // core clock after reset, hsi or sysclk of qemu
+    pub clock: u32,
+    // input clock of tim2 and tim3
+    pub timer_clock: u32,
+    // resolution of the sleep timer
+    pub ticks_per_ms: u32,
+    // port and pin of each led, driven as push pull output. the leds
+    // are user pins, tasks claim them through the gpio syscalls.
+    pub leds: &'static [(&'static str, u8)],
+    // port, pin and alternate function of the usart1 tx line
+    pub console_tx: (&'static str, u8, u32),
+    pub baudrate: u32,
+}
+
+#[cfg(not(any(feature = "f407", feature = "qemu", feature = "microbit")))]
+pub const BOARD: Board = Board {
+    name: "stm32f3 discovery",
+    clock: 8_000_000,
+    timer_clock: 8_000_000,
+    ticks_per_ms: 8,
+    // ld3 - ld10 are found at pe8 - pe15, we use four of them
+    leds: &[("E", 11), ("E", 12), ("E", 13), ("E", 14)],
+    console_tx: ("A", 9, 7),
+    baudrate: 9600,
+};
+
+#[cfg(feature = "f407")]
+pub const BOARD: Board = Board {
+    name: "stm32f407vet6",
+    // hsi, the pll stays off
+    clock: 16_000_000,
+    timer_clock: 16_000_000,
+    ticks_per_ms: 8,
+    // d2 and d3, low active
+    leds: &[("A", 6), ("A", 7)],
+    console_tx: ("A", 9, 7),
+    baudrate: 9600,
+};
+
+#[cfg(feature = "qemu")]
+pub const BOARD: Board = Board {
+    name: "qemu netduinoplus2",
+    clock: 168_000_000,
+    // qemu clocks the timers with 1 ghz
+    timer_clock: 1_000_000_000,
+    ticks_per_ms: 20,
+    leds: &[],
+    console_tx: ("A", 9, 7),
+    baudrate: 9600,
+};
+
+#[cfg(feature = "microbit")]
+pub const BOARD: Board = Board {
+    name: "bbc micro:bit",
+    clock: 16_000_000,
+    timer_clock: 16_000_000,
+    // the prescaler of the nrf51 divides by powers of two only,
+    // 16 mhz / 128. a single sleep lasts at most 524 ms.
+    ticks_per_ms: 125,
+    // the leds form a matrix of rows and columns, left unused
+    leds: &[],
+    // p0.24, connected to the usb interface chip
+    console_tx: ("0", 24, 0),
+    baudrate: 9600,
+};
+
+// timer 3 counts the sleep time of the tasks in steps of 1 / TICKS_PER_MS ms
+pub mod timing {
+    use super::BOARD;
+
+    pub const TICKS_PER_MS: u32 = BOARD.ticks_per_ms;
+    // timer_clock / (PRESCALER + 1) = ticks per second
+    pub const PRESCALER: u16 = (BOARD.timer_clock / (BOARD.ticks_per_ms * 1000) - 1) as u16;
+    // timer 2 counts microseconds for the time measurement
+    pub const US_PRESCALER: u16 = (BOARD.timer_clock / 1_000_000 - 1) as u16;
+}
+
+///
+/// Turns on the clocks of the used peripherals, configures the
+/// leds and the console. Gets called once by `Reset`.
+///
+#[cfg(not(feature = "microbit"))]
+pub fn init() {
+    let (console_port, console_pin, console_af) = BOARD.console_tx;
+
+    // turn on the clocks of the gpio ports in use
+    rcc::AHBENR.set(rcc::iopen(port_index(console_port)));
+    for (port, _) in BOARD.leds {
+        rcc::AHBENR.set(rcc::iopen(port_index(port)));
+    }
+    rcc::APB1ENR.modify(|r| r | rcc::TIM2EN.mask() | rcc::TIM3EN.mask());
+    rcc::APB2ENR.modify(|r| r | rcc::USART1EN.mask() | rcc::SYSCFGEN.mask());
+
+    // the console pin can not be taken as `gpio` pin anymore. the leds
+    // used to be reserved as well, now they are left free on purpose:
+    // they are meant for the tasks, which claim them with
+    // `call_api::gpio_claim`. a kernel driver may still take them.
+    gpio::reserve(port_index(console_port), console_pin);
+
+    for (port, pin) in BOARD.leds {
+        if let Some(led) = GpioX::new(port, *pin) {
+            led.set_moder(gpio_types::ModerTypes::GeneralPurposeOutputMode);
+            led.set_otyper(gpio_types::OutputTypes::PushPull);
+        }
+    }
+
+    if let Some(tx) = GpioX::new(console_port, console_pin) {
+        tx.set_moder(gpio_types::ModerTypes::AlternateFunctionMode);
+        tx.set_otyper(gpio_types::OutputTypes::PushPull);
+        tx.into_af(console_af);
+    }
+
+    uart::new(1, BOARD.baudrate).enable();
+}
+
+///
+/// The peripherals of the nrf51 are clocked as soon as they get
+/// enabled, only the console needs its tx pin.
+///
+#[cfg(feature = "microbit")]
+pub fn init() {
+    let (_, console_pin, _) = BOARD.console_tx;
+    uart0::PSELTXD.write(console_pin as u32);
+
+    uart::new(1, BOARD.baudrate).enable();
+}
+
+// port "A" is 0, "B" is 1 and so on
+#[cfg(not(feature = "microbit"))]
+fn port_index(port_mnemonic: &str) -> u32 {
+    (port_mnemonic.as_bytes()[0] - b'A') as u32
+}
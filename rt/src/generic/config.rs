@@ -0,0 +1,10 @@
+//!
+//! Build time configuration of the kernel tables. The values
+//! get generated by build.rs and may be changed with the
+//! environment variables RT_MAX_TASKS, RT_TASK_STACK_SIZE and
+//! RT_HEAP_SIZE (both in bytes). MAX_TASKS counts the idle task
+//! of the scheduler as well. FLASH_SIZE and SRAM_SIZE
+//! describe the memory of the selected board.
+//! Tables too large for the SRAM let the link step fail.
+//!
+include!(concat!(env!("OUT_DIR"), "/config.rs"));
@@ -1,16 +1,47 @@
 pub mod system {
-    pub const CLOCK: u32 = 8_000_000;
+    // core clock of the selected board
+    pub const CLOCK: u32 = crate::generic::board::BOARD.clock;
 }
 
 pub mod c_adresses {
     // page 244
     pub const SCB: u32 = 0xE000_ED00;
     pub const STK: u32 = 0xE000_E010;
+    // page 208
+    pub const NVIC: u32 = 0xE000_E100;
+    // page 195
+    pub const MPU: u32 = 0xE000_ED90;
+    // page 264
+    pub const FPU: u32 = 0xE000_EF30;
 }
 
 pub mod c_offsets {
     pub mod scb {
         pub const ICSR: u32 = 0x04;
+        pub const AIRCR: u32 = 0x0C;
+        pub const CCR: u32 = 0x14;
+        pub const SHCSR: u32 = 0x24;
+        pub const CFSR: u32 = 0x28;
+        pub const HFSR: u32 = 0x2C;
+        pub const MMFAR: u32 = 0x34;
+        pub const BFAR: u32 = 0x38;
+        pub const CPACR: u32 = 0x88;
+    }
+    pub mod mpu {
+        pub const TYPE: u32 = 0x00;
+        pub const CTRL: u32 = 0x04;
+        pub const RNR: u32 = 0x08;
+        pub const RBAR: u32 = 0x0C;
+        pub const RASR: u32 = 0x10;
+    }
+    pub mod fpu {
+        pub const FPCCR: u32 = 0x04;
+    }
+    pub mod nvic {
+        pub const ISER: u32 = 0x000;
+        pub const ICER: u32 = 0x080;
+        pub const ISPR: u32 = 0x100;
+        pub const ICPR: u32 = 0x180;
     }
     pub mod stk {
         pub const CTRL: u32 = 0x00;
@@ -21,10 +52,170 @@ pub mod c_offsets {
 }
 
 pub mod c_bitfields {
-    pub mod icsr {
-        pub const PENDSVSET: u32 = 28;
+    // value of lr when entering an exception, p 42
+    pub mod exc_return {
+        // return to thread mode, basic frame on the psp
+        pub const THREAD_PSP: u32 = 0xFFFF_FFFD;
+        // set if the exception frame was stacked on the psp
+        pub const SPSEL: u32 = 2;
+        // cleared if the frame got extended by the fpu registers
+        pub const FTYPE: u32 = 4;
     }
+}
+
+// typed registers of the core peripherals, see generic::reg
+pub mod c_registers {
+    pub mod scb {
+        use crate::generic::cpu::{c_adresses, c_offsets};
+        use crate::generic::reg::{Field, Reg, RO, RW};
+
+        pub const ICSR: Reg<RW> = Reg::at(c_adresses::SCB | c_offsets::scb::ICSR);
+        pub const AIRCR: Reg<RW> = Reg::at(c_adresses::SCB | c_offsets::scb::AIRCR);
+        pub const CCR: Reg<RW> = Reg::at(c_adresses::SCB | c_offsets::scb::CCR);
+        pub const SHCSR: Reg<RW> = Reg::at(c_adresses::SCB | c_offsets::scb::SHCSR);
+        // the fault status bits get cleared by writing ones
+        pub const CFSR: Reg<RW> = Reg::at(c_adresses::SCB | c_offsets::scb::CFSR);
+        pub const HFSR: Reg<RW> = Reg::at(c_adresses::SCB | c_offsets::scb::HFSR);
+        pub const MMFAR: Reg<RO> = Reg::at(c_adresses::SCB | c_offsets::scb::MMFAR);
+        pub const BFAR: Reg<RO> = Reg::at(c_adresses::SCB | c_offsets::scb::BFAR);
+        pub const CPACR: Reg<RW> = Reg::at(c_adresses::SCB | c_offsets::scb::CPACR);
+
+        // icsr
+        pub const VECTACTIVE: Field = Field::new(0, 9);
+        pub const PENDSVSET: Field = Field::bit(28);
+        // aircr, writes without the key in the upper half get ignored
+        pub const VECTKEY: Field = Field::new(16, 16);
+        pub const VECTKEY_VALUE: u32 = 0x05FA;
+        pub const SYSRESETREQ: Field = Field::bit(2);
+        pub const PRIGROUP: Field = Field::new(8, 3);
+        // ccr
+        pub const DIV_0_TRP: Field = Field::bit(4);
+        // shcsr
+        pub const MEMFAULTENA: Field = Field::bit(16);
+        pub const BUSFAULTENA: Field = Field::bit(17);
+        pub const USGFAULTENA: Field = Field::bit(18);
+        // cpacr, full access for coprocessors 10 and 11, which form the fpu
+        pub const CP10_CP11: Field = Field::new(20, 4);
+        pub const CP_FULL_ACCESS: u32 = 0xF;
+
+        // configurable fault status register, p 237
+        pub mod cfsr {
+            use crate::generic::reg::Field;
+
+            pub const IACCVIOL: Field = Field::bit(0);
+            pub const DACCVIOL: Field = Field::bit(1);
+            pub const MUNSTKERR: Field = Field::bit(3);
+            pub const MSTKERR: Field = Field::bit(4);
+            pub const MLSPERR: Field = Field::bit(5);
+            pub const MMARVALID: Field = Field::bit(7);
+            pub const IBUSERR: Field = Field::bit(8);
+            pub const PRECISERR: Field = Field::bit(9);
+            pub const IMPRECISERR: Field = Field::bit(10);
+            pub const UNSTKERR: Field = Field::bit(11);
+            pub const STKERR: Field = Field::bit(12);
+            pub const LSPERR: Field = Field::bit(13);
+            pub const BFARVALID: Field = Field::bit(15);
+            pub const UNDEFINSTR: Field = Field::bit(16);
+            pub const INVSTATE: Field = Field::bit(17);
+            pub const INVPC: Field = Field::bit(18);
+            pub const NOCP: Field = Field::bit(19);
+            pub const UNALIGNED: Field = Field::bit(24);
+            pub const DIVBYZERO: Field = Field::bit(25);
+        }
+        pub mod hfsr {
+            use crate::generic::reg::Field;
+
+            pub const VECTTBL: Field = Field::bit(1);
+            pub const FORCED: Field = Field::bit(30);
+        }
+    }
+
+    pub mod mpu {
+        use crate::generic::cpu::{c_adresses, c_offsets};
+        use crate::generic::reg::{Field, Reg, RO, RW};
+
+        pub const TYPE: Reg<RO> = Reg::at(c_adresses::MPU | c_offsets::mpu::TYPE);
+        pub const CTRL: Reg<RW> = Reg::at(c_adresses::MPU | c_offsets::mpu::CTRL);
+        pub const RNR: Reg<RW> = Reg::at(c_adresses::MPU | c_offsets::mpu::RNR);
+        pub const RBAR: Reg<RW> = Reg::at(c_adresses::MPU | c_offsets::mpu::RBAR);
+        pub const RASR: Reg<RW> = Reg::at(c_adresses::MPU | c_offsets::mpu::RASR);
+
+        // type, number of regions, 0 without mpu
+        pub const DREGION: Field = Field::new(8, 8);
+        // ctrl
+        pub const ENABLE: Field = Field::bit(0);
+        // privileged code may access everything not covered by a region
+        pub const PRIVDEFENA: Field = Field::bit(2);
+        // rbar, regions are aligned to at least 32 bytes
+        pub const ADDR: Field = Field::new(5, 27);
+        // rasr
+        pub const REGION_ENABLE: Field = Field::bit(0);
+        pub const SIZE: Field = Field::new(1, 5);
+        pub const B: Field = Field::bit(16);
+        pub const C: Field = Field::bit(17);
+        pub const S: Field = Field::bit(18);
+        pub const AP: Field = Field::new(24, 3);
+        pub const XN: Field = Field::bit(28);
+        // access permissions, p 193
+        pub const AP_NO_ACCESS: u32 = 0b000;
+        pub const AP_PRIV_RW: u32 = 0b001;
+        pub const AP_FULL_ACCESS: u32 = 0b011;
+        pub const AP_READ_ONLY: u32 = 0b110;
+    }
+
+    pub mod fpu {
+        use crate::generic::cpu::{c_adresses, c_offsets};
+        use crate::generic::reg::{Field, Reg, RW};
+
+        pub const FPCCR: Reg<RW> = Reg::at(c_adresses::FPU | c_offsets::fpu::FPCCR);
+
+        pub const LSPEN: Field = Field::bit(30);
+        pub const ASPEN: Field = Field::bit(31);
+    }
+
     pub mod stk {
-        pub const ENABLE: u32 = 0b1;
+        use crate::generic::cpu::{c_adresses, c_offsets};
+        use crate::generic::reg::{Field, Reg, RW};
+
+        pub const CTRL: Reg<RW> = Reg::at(c_adresses::STK | c_offsets::stk::CTRL);
+        pub const LOAD: Reg<RW> = Reg::at(c_adresses::STK | c_offsets::stk::LOAD);
+        pub const VAL: Reg<RW> = Reg::at(c_adresses::STK | c_offsets::stk::VAL);
+
+        // ctrl
+        pub const ENABLE: Field = Field::bit(0);
+        pub const TICKINT: Field = Field::bit(1);
+        pub const CLKSOURCE: Field = Field::bit(2);
+        // load and val
+        pub const RELOAD: Field = Field::new(0, 24);
     }
-}
\ No newline at end of file
+
+    pub mod nvic {
+        use crate::generic::cpu::{c_adresses, c_offsets};
+        use crate::generic::reg::{Reg, WO};
+
+        // every register of a kind holds 32 interrupt lines, so
+        // irq n is found in word n / 32 at bit n % 32. the set and
+        // clear registers ignore written zeros, therefore they
+        // are treated as write only.
+        const fn line(offset: u32, irq: usize) -> Reg<WO> {
+            Reg::at(c_adresses::NVIC + offset + (irq as u32 / 32) * 4)
+        }
+
+        pub const fn iser(irq: usize) -> Reg<WO> {
+            line(c_offsets::nvic::ISER, irq)
+        }
+        pub const fn icer(irq: usize) -> Reg<WO> {
+            line(c_offsets::nvic::ICER, irq)
+        }
+        pub const fn ispr(irq: usize) -> Reg<WO> {
+            line(c_offsets::nvic::ISPR, irq)
+        }
+        pub const fn icpr(irq: usize) -> Reg<WO> {
+            line(c_offsets::nvic::ICPR, irq)
+        }
+
+        pub const fn bit(irq: usize) -> u32 {
+            0b1 << (irq as u32 % 32)
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//!
+//! The kernel as a linux program, for debugging with the tools of
+//! the host. Every task runs on a thread of its own, but only the
+//! thread holding the cpu executes while all others wait. The cpu
+//! changes hands whenever the kernel loads a task, i.e. within pendsv.
+//! A thread can not get interrupted, so interrupts get delivered when
+//! the running task enters a syscall or the idle task waits for them.
+//!
+//! A clock thread takes the place of the timers. It raises the systick
+//! according to its reload value and the interrupt of timer 3 once
+//! a sleep is over. Everything written to usart1 appears on stdout.
+//!
+//! The process table stores 32 bit adresses, syscalls which pass
+//! adresses back to the task (heap, pools) need a 32 bit host.
+//!
+use crate::generic::board::timing;
+use crate::generic::cpu::c_bitfields::exc_return;
+use crate::generic::cpu::c_registers::{scb, stk};
+use crate::generic::cpu::system;
+use crate::generic::platform::irqn;
+use crate::generic::platform::registers::{tim, usart};
+use crate::hal::Hal;
+use crate::sched::{process, task_control};
+use crate::sys::call_api::{self, TrapMeta};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// the first 16 vectors are reserved for the cpu exceptions
+const EXTERNAL_OFFSET: u32 = 16;
+const NO_TASK: u32 = u32::MAX;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Exception {
+    SysTick,
+    Irq(usize),
+}
+
+struct Cpu {
+    // pid of the task whose thread may run
+    running: u32,
+    // task loaded by the scheduler, it takes over the cpu
+    // once the handler which loaded it has returned
+    loaded: u32,
+    // raised but not yet handled, in order of occurrence
+    pending: Vec<Exception>,
+}
+
+struct Hardware {
+    registers: BTreeMap<u32, u32>,
+    // ms since the last systick
+    systick_elapsed: u32,
+    // ms until timer 3 reaches its reload value, None while it stands still
+    tim3_left: Option<u32>,
+}
+
+static CPU: Mutex<Cpu> = Mutex::new(Cpu {
+    running: NO_TASK,
+    loaded: NO_TASK,
+    pending: Vec::new(),
+});
+static CPU_CHANGED: Condvar = Condvar::new();
+static HARDWARE: Mutex<Hardware> = Mutex::new(Hardware {
+    registers: BTreeMap::new(),
+    systick_elapsed: 0,
+    tim3_left: None,
+});
+// pid and entry point of a task created with `create`
+type Entry = (u32, fn());
+static ENTRIES: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+thread_local! {
+    // pid of the task running on this thread
+    static PID: Cell<u32> = const { Cell::new(NO_TASK) };
+}
+
+///
+/// Register map of the hosted port. Registers which were never
+/// written read as 0, the transmitter of usart1 is always ready.
+///
+pub struct Peripherals;
+
+impl Hal for Peripherals {
+    fn read(adress: u32) -> u32 {
+        if adress == usart::USART1.isr().adress() {
+            return usart::TXE.mask();
+        }
+        let hardware = HARDWARE.lock().unwrap();
+        register(&hardware, adress)
+    }
+
+    fn write(adress: u32, value: u32) {
+        let mut hardware = HARDWARE.lock().unwrap();
+        hardware.registers.insert(adress, value);
+
+        if adress == usart::USART1.tdr().adress() {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(&[value as u8]);
+            if value as u8 == b'\n' {
+                let _ = stdout.flush();
+            }
+        } else if adress == tim::TIM3.cr1().adress() {
+            // the sleep time starts when the counter gets enabled
+            let enabled = value & tim::CEN.mask() != 0;
+            hardware.tim3_left = match (enabled, hardware.tim3_left) {
+                (true, None) => {
+                    let reload = register(&hardware, tim::TIM3.arr().adress()) & tim::RELOAD.mask();
+                    Some(reload.div_ceil(timing::TICKS_PER_MS).max(1))
+                }
+                (true, left) => left,
+                (false, _) => None,
+            };
+        }
+    }
+}
+
+fn register(hardware: &Hardware, adress: u32) -> u32 {
+    hardware.registers.get(&adress).copied().unwrap_or(0)
+}
+
+///
+/// Counterpart of `Reset`, sets up the board and the sleep timer.
+/// A panic ends the program like the reset of a board would.
+///
+pub fn boot() {
+    std::panic::set_hook(Box::new(|info| {
+        eprintln!("\n---- !!!KERNEL PANIC!!! ----\n{}", info);
+        std::process::exit(101);
+    }));
+    crate::generic::board::init();
+    crate::print_banner();
+    crate::start_sleep_timer();
+}
+
+///
+/// Creates a task like `process::create` does. The process table
+/// can not hold a 64 bit entry point, so it gets kept here as well.
+///
+pub fn create(task: fn()) -> Option<u32> {
+    let pid = process::create(task as usize as u32)?;
+    ENTRIES.lock().unwrap().push((pid, task));
+    Some(pid)
+}
+
+///
+/// Starts a thread for every task and the clock, the task loaded by
+/// `scheduler::load` runs first. Only the exit syscall ends the program.
+///
+pub fn run() -> ! {
+    hand_over();
+    for pid in 0..task_control::task_count() {
+        let entry = ENTRIES.lock().unwrap().iter().find(|(p, _)| *p == pid).map(|(_, e)| *e);
+        thread::spawn(move || {
+            PID.with(|p| p.set(pid));
+            wait_for_cpu(pid);
+            match entry {
+                Some(task) => {
+                    task();
+                    call_api::terminate();
+                }
+                // created by the kernel itself, i.e. the idle task
+                None => loop {
+                    __wait_for_interrupt();
+                },
+            }
+        });
+    }
+    thread::spawn(clock);
+    loop {
+        thread::park();
+    }
+}
+
+pub fn exit(code: u32) {
+    let _ = std::io::stdout().flush();
+    std::process::exit(code as i32);
+}
+
+// raises the systick and the interrupt of timer 3, ticks every ms.
+// the ticks follow the wall clock, a late one gets caught up.
+fn clock() {
+    let mut tick = Instant::now();
+    loop {
+        tick += Duration::from_millis(1);
+        thread::sleep(tick.saturating_duration_since(Instant::now()));
+        let mut raised = Vec::new();
+        {
+            let mut hardware = HARDWARE.lock().unwrap();
+            if register(&hardware, stk::CTRL.adress()) & stk::ENABLE.mask() != 0 {
+                let reload = register(&hardware, stk::LOAD.adress()) & stk::RELOAD.mask();
+                let period = (reload / (system::CLOCK / 1000)).max(1);
+                hardware.systick_elapsed += 1;
+                if hardware.systick_elapsed >= period {
+                    hardware.systick_elapsed = 0;
+                    raised.push(Exception::SysTick);
+                }
+            }
+            hardware.tim3_left = match hardware.tim3_left {
+                Some(left) if left > 1 => Some(left - 1),
+                Some(_) => {
+                    raised.push(Exception::Irq(irqn::TIM3));
+                    None
+                }
+                None => None,
+            };
+        }
+        if !raised.is_empty() {
+            let mut cpu = CPU.lock().unwrap();
+            for exception in raised {
+                // like the nvic, an exception is either pending or not
+                if !cpu.pending.contains(&exception) {
+                    cpu.pending.push(exception);
+                }
+            }
+            CPU_CHANGED.notify_all();
+        }
+    }
+}
+
+// runs the handlers of the pending exceptions on the calling thread
+fn handle_exceptions() {
+    loop {
+        let next = {
+            let mut cpu = CPU.lock().unwrap();
+            match cpu.pending.is_empty() {
+                true => None,
+                false => Some(cpu.pending.remove(0)),
+            }
+        };
+        match next {
+            Some(Exception::SysTick) => crate::SysTick(),
+            // the dispatcher looks up the active vector
+            Some(Exception::Irq(irq)) => {
+                scb::ICSR.write_field(scb::VECTACTIVE, EXTERNAL_OFFSET + irq as u32);
+                crate::interrupts::irq::dispatch();
+                scb::ICSR.write_field(scb::VECTACTIVE, 0);
+            }
+            None => break,
+        }
+    }
+    // pendsv has the lowest priority, it runs after every other handler
+    if scb::ICSR.is_set(scb::PENDSVSET) {
+        scb::ICSR.clear(scb::PENDSVSET);
+        // there are no exception frames, every task runs in thread mode
+        crate::ContextSwitch(exc_return::THREAD_PSP);
+    }
+}
+
+fn hand_over() {
+    let mut cpu = CPU.lock().unwrap();
+    if cpu.loaded != NO_TASK {
+        cpu.running = cpu.loaded;
+        cpu.loaded = NO_TASK;
+        CPU_CHANGED.notify_all();
+    }
+}
+
+fn wait_for_cpu(pid: u32) {
+    let mut cpu = CPU.lock().unwrap();
+    while cpu.running != pid {
+        cpu = CPU_CHANGED.wait(cpu).unwrap();
+    }
+}
+
+// handles the pending exceptions, which may load another task.
+// the calling thread continues once its task gets loaded again.
+fn reschedule() {
+    handle_exceptions();
+    hand_over();
+    wait_for_cpu(PID.with(|p| p.get()));
+}
+
+///
+/// Marks the task selected by the scheduler to run next.
+///
+#[no_mangle]
+pub extern "C" fn __load_process_context(_addr: u32, _exc_return: u32) {
+    CPU.lock().unwrap().loaded = task_control::current_pid();
+}
+
+#[no_mangle]
+pub extern "C" fn __wait_for_interrupt() {
+    {
+        let mut cpu = CPU.lock().unwrap();
+        while cpu.pending.is_empty() {
+            cpu = CPU_CHANGED.wait(cpu).unwrap();
+        }
+    }
+    reschedule();
+}
+
+///
+/// Entry of every syscall. Interrupts raised while the task was
+/// running get handled first, as if they preempted it right before.
+///
+#[no_mangle]
+pub extern "C" fn __trap(trap_id: &TrapMeta) {
+    reschedule();
+    crate::handle_trap(trap_id);
+    reschedule();
+}
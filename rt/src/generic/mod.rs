@@ -1,2 +1,13 @@
+pub mod board;
+#[cfg(not(feature = "microbit"))]
 pub mod platform;
-pub mod cpu;
\ No newline at end of file
+// the nrf51 shares none of the stm32 peripherals
+#[cfg(feature = "microbit")]
+#[path = "platform/nrf51.rs"]
+pub mod platform;
+pub mod cpu;
+pub mod config;
+pub mod reg;
+// the kernel as linux program, see `hosted::run`
+#[cfg(all(feature = "hosted", not(target_os = "none")))]
+pub mod hosted;
@@ -1,50 +1,379 @@
-pub mod adresses {
-    pub const TIM2_BASEADRESS: u32 = 0x4000_0000;
-    pub const TIM3_BASEADRESS: u32 = 0x4000_0400;
-    pub const USART1_BASEADRESS: u32 = 0x4001_3800;
+//!
+//! Peripherals of the stm32 chips. The timers and the gpio ports
+//! look the same on every supported chip, the adresses of the
+//! remaining peripherals, the rcc bits and the irq map come from
+//! the chip module selected by the board feature, see `board`.
+//!
+#[cfg(not(any(feature = "f407", feature = "qemu")))]
+mod stm32f3;
+#[cfg(not(any(feature = "f407", feature = "qemu")))]
+use self::stm32f3 as chip;
+#[cfg(any(feature = "f407", feature = "qemu"))]
+mod stm32f4;
+#[cfg(any(feature = "f407", feature = "qemu"))]
+use self::stm32f4 as chip;
 
+pub mod adresses {
+    pub use super::chip::adresses::*;
 
-    // manuel page 55
-    pub const RCC: u32 = 0x4002_1000;
+    // memory map, datasheet page 53
+    pub const FLASH: u32 = 0x0800_0000;
+    pub const SRAM: u32 = 0x2000_0000;
 
+    pub const TIM2_BASEADRESS: u32 = 0x4000_0000;
+    pub const TIM3_BASEADRESS: u32 = 0x4000_0400;
 }
 
 pub mod offsets {
-    pub mod rcc {
-        pub const RCC_APB1RSTR: u32 = 0x10;
-        pub const RCC_AHBENR: u32 = 0x14;
-        pub const RCC_APB2ENR: u32 = 0x18;
-        pub const RCC_APB1ENR: u32 = 0x1C;
-    }
-    pub mod usart1 {
-        pub const TDR: u32 = 0x28;
-        pub const ISR: u32 = 0x1C;
-        pub const BRR: u32 = 0x0C;
-    }
+    pub use super::chip::offsets::*;
+
     pub mod tim {
+        pub const CR1: u32 = 0x00;
         pub const DIER: u32 = 0x0C;
         pub const SR: u32 = 0x10;
         pub const EGR: u32 = 0x14;
         pub const CNT: u32 = 0x24;
         pub const PSC: u32 = 0x28;
+        pub const CCMR1: u32 = 0x18;
+        pub const CCMR2: u32 = 0x1C;
+        pub const CCER: u32 = 0x20;
+        pub const ARR: u32 = 0x2C;
+        // ccr2 - ccr4 follow ccr1
         pub const CCR1: u32 = 0x34;
-
+        pub const BDTR: u32 = 0x44;
+    }
+    // p 237 ff
+    pub mod gpio {
+        pub const MODER: u32 = 0x00;
+        pub const OTYPER: u32 = 0x04;
+        pub const OSPEEDR: u32 = 0x08;
+        pub const PUPDR: u32 = 0x0C;
+        pub const IDR: u32 = 0x10;
+        pub const ODR: u32 = 0x14;
+        pub const BSRR: u32 = 0x18;
+        pub const AFRL: u32 = 0x20;
+        pub const AFRH: u32 = 0x24;
+    }
+    // rm0316 p 245, the f4 uses the same layout
+    pub mod syscfg {
+        pub const EXTICR1: u32 = 0x08;
+    }
+    // rm0316 p 297
+    pub mod exti {
+        pub const IMR: u32 = 0x00;
+        pub const RTSR: u32 = 0x08;
+        pub const FTSR: u32 = 0x0C;
+        pub const PR: u32 = 0x14;
     }
 }
 
+pub use self::chip::irqn;
+
 pub mod bitfields {
-    pub mod rcc {
-        pub const SYSCFGEN: u32 = 1;
-        pub const IOPAEN: u32 = 17;
-        pub const IOPEEN: u32 = 21;
-        pub const USART1EN: u32 = 14;
-    } 
-    pub mod usart1 {
-    }
     pub mod tim {
         pub const CEN: u32 = 0b1;
         pub const UG: u32 = 0b1;
         pub const UDIS: u32 = 0b10;
         pub const CC1IE: u32 = 0b10;
     }
-}
\ No newline at end of file
+}
+
+// typed registers of the peripherals, see generic::reg
+pub mod registers {
+    pub mod rcc {
+        use crate::generic::platform::{adresses, offsets};
+        use crate::generic::reg::{Field, Reg, RW};
+
+        pub const APB1RSTR: Reg<RW> = Reg::at(adresses::RCC | offsets::rcc::RCC_APB1RSTR);
+        pub const APB2RSTR: Reg<RW> = Reg::at(adresses::RCC | offsets::rcc::RCC_APB2RSTR);
+        pub const AHBENR: Reg<RW> = Reg::at(adresses::RCC | offsets::rcc::RCC_AHBENR);
+        pub const APB2ENR: Reg<RW> = Reg::at(adresses::RCC | offsets::rcc::RCC_APB2ENR);
+        pub const APB1ENR: Reg<RW> = Reg::at(adresses::RCC | offsets::rcc::RCC_APB1ENR);
+
+        // apb1rstr, p 166
+        pub const TIM2RST: Field = Field::bit(0);
+        pub const TIM3RST: Field = Field::bit(1);
+        // apb1enr
+        pub const TIM2EN: Field = Field::bit(0);
+        pub const TIM3EN: Field = Field::bit(1);
+        // ahbenr and apb2enr
+        pub use crate::generic::platform::chip::fields::rcc::*;
+    }
+
+    pub mod tim {
+        use crate::generic::platform::offsets;
+        use crate::generic::reg::{Field, Reg, RW};
+
+        // the bus a timer is clocked by, selects the rcc registers
+        #[derive(Clone, Copy, PartialEq)]
+        pub enum Bus {
+            Apb1,
+            Apb2,
+        }
+
+        ///
+        /// A timer instance. The instances of the selected chip
+        /// are found in this module as well, e.g. `TIM3`.
+        ///
+        #[derive(Clone, Copy)]
+        pub struct Tim {
+            pub(crate) base: u32,
+            pub(crate) bus: Bus,
+            // enable bit in apbxenr, reset bit in apbxrstr
+            pub(crate) bit: u32,
+            // update interrupt, capture/compare interrupt
+            pub irq: usize,
+            pub cc_irq: usize,
+            // bits of the counter, 16 or 32
+            pub width: u32,
+            // capture/compare channels, 0 for the basic timers
+            pub channels: u8,
+            // complementary outputs and break, tim1 and tim8
+            pub advanced: bool,
+        }
+
+        pub use crate::generic::platform::chip::timers::*;
+
+        impl Tim {
+            pub const fn cr1(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::tim::CR1)
+            }
+            pub const fn dier(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::tim::DIER)
+            }
+            // flags get cleared by writing 0, written ones are ignored
+            pub const fn sr(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::tim::SR)
+            }
+            pub const fn egr(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::tim::EGR)
+            }
+            pub const fn cnt(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::tim::CNT)
+            }
+            pub const fn psc(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::tim::PSC)
+            }
+            pub const fn arr(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::tim::ARR)
+            }
+            pub const fn ccr1(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::tim::CCR1)
+            }
+            // channels 1 - 4
+            pub const fn ccr(&self, channel: u8) -> Reg<RW> {
+                Reg::at(self.base | (offsets::tim::CCR1 + (channel as u32 - 1) * 4))
+            }
+            // channels 1 and 2 in ccmr1, 3 and 4 in ccmr2
+            pub const fn ccmr(&self, channel: u8) -> Reg<RW> {
+                match channel {
+                    1 | 2 => Reg::at(self.base | offsets::tim::CCMR1),
+                    _ => Reg::at(self.base | offsets::tim::CCMR2),
+                }
+            }
+            pub const fn ccer(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::tim::CCER)
+            }
+            // advanced timers only
+            pub const fn bdtr(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::tim::BDTR)
+            }
+        }
+
+        // cr1
+        pub const CEN: Field = Field::bit(0);
+        pub const UDIS: Field = Field::bit(1);
+        // only an overflow of the counter raises the update interrupt, ug does not
+        pub const URS: Field = Field::bit(2);
+        // one pulse mode, the counter stops at the next update
+        pub const OPM: Field = Field::bit(3);
+        // arr gets buffered until the next update
+        pub const ARPE: Field = Field::bit(7);
+        // dier
+        pub const UIE: Field = Field::bit(0);
+        pub const CC1IE: Field = Field::bit(1);
+        // sr, the capture/compare flags 1 - 4 and the trigger flag
+        pub const UIF: Field = Field::bit(0);
+        pub const CCXIF: Field = Field::new(1, 4);
+        pub const TIF: Field = Field::bit(6);
+        // ccxie in dier and ccxif in sr of channels 1 - 4
+        pub const fn cc(channel: u8) -> Field {
+            Field::bit(channel as u32)
+        }
+        // ccmr, each register holds two channels in 8 bits each
+        const fn ccmr_offset(channel: u8) -> u32 {
+            ((channel as u32 - 1) % 2) * 8
+        }
+        // 00 output, 01 input from its own pin
+        pub const fn cc_select(channel: u8) -> Field {
+            Field::new(ccmr_offset(channel), 2)
+        }
+        pub const fn oc_preload(channel: u8) -> Field {
+            Field::bit(ccmr_offset(channel) + 3)
+        }
+        pub const fn oc_mode(channel: u8) -> Field {
+            Field::new(ccmr_offset(channel) + 4, 3)
+        }
+        // input capture, every 1st, 2nd, 4th or 8th edge
+        pub const fn ic_prescaler(channel: u8) -> Field {
+            Field::new(ccmr_offset(channel) + 2, 2)
+        }
+        // number of samples an edge has to be stable for
+        pub const fn ic_filter(channel: u8) -> Field {
+            Field::new(ccmr_offset(channel) + 4, 4)
+        }
+        // oc_mode, active while the counter is below the compare value
+        pub const PWM_MODE_1: u32 = 0b110;
+        // ccer, four bits per channel: enable, polarity and the
+        // same for the complementary output
+        pub const fn cc_enable(channel: u8) -> Field {
+            Field::bit((channel as u32 - 1) * 4)
+        }
+        pub const fn cc_polarity(channel: u8) -> Field {
+            Field::bit((channel as u32 - 1) * 4 + 1)
+        }
+        pub const fn ccn_enable(channel: u8) -> Field {
+            Field::bit((channel as u32 - 1) * 4 + 2)
+        }
+        pub const fn ccn_polarity(channel: u8) -> Field {
+            Field::bit((channel as u32 - 1) * 4 + 3)
+        }
+        // bdtr, main output enable of the advanced timers
+        pub const MOE: Field = Field::bit(15);
+        // egr
+        pub const UG: Field = Field::bit(0);
+        // cnt, bit 31 mirrors uif if uifremap is set
+        pub const COUNT: Field = Field::new(0, 31);
+        pub const PRESCALER: Field = Field::new(0, 16);
+        pub const RELOAD: Field = Field::new(0, 16);
+    }
+
+    pub mod usart {
+        use crate::generic::platform::{adresses, offsets};
+        use crate::generic::reg::{Field, Reg, RO, RW, WO};
+
+        pub struct Usart {
+            base: u32,
+        }
+
+        pub const USART1: Usart = Usart::at(adresses::USART1_BASEADRESS);
+
+        impl Usart {
+            pub const fn at(base: u32) -> Usart {
+                Usart { base }
+            }
+            pub const fn cr1(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::usart1::CR1)
+            }
+            pub const fn brr(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::usart1::BRR)
+            }
+            pub const fn isr(&self) -> Reg<RO> {
+                Reg::at(self.base | offsets::usart1::ISR)
+            }
+            pub const fn tdr(&self) -> Reg<WO> {
+                Reg::at(self.base | offsets::usart1::TDR)
+            }
+        }
+
+        // cr1
+        pub use crate::generic::platform::chip::fields::usart::UE;
+        pub const RE: Field = Field::bit(2);
+        pub const TE: Field = Field::bit(3);
+        // isr
+        pub const TXE: Field = Field::bit(7);
+    }
+
+    pub mod gpio {
+        use crate::generic::platform::offsets;
+        use crate::generic::reg::{Field, Reg, RO, RW, WO};
+
+        pub struct Gpio {
+            base: u32,
+        }
+
+        impl Gpio {
+            pub const fn at(base: u32) -> Gpio {
+                Gpio { base }
+            }
+            pub const fn moder(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::gpio::MODER)
+            }
+            pub const fn otyper(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::gpio::OTYPER)
+            }
+            pub const fn ospeedr(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::gpio::OSPEEDR)
+            }
+            pub const fn pupdr(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::gpio::PUPDR)
+            }
+            pub const fn idr(&self) -> Reg<RO> {
+                Reg::at(self.base | offsets::gpio::IDR)
+            }
+            pub const fn odr(&self) -> Reg<RW> {
+                Reg::at(self.base | offsets::gpio::ODR)
+            }
+            // ones in the lower half set the pin, in the upper half reset it.
+            // the brr of the f3 equals the upper half, the f4 lacks it.
+            pub const fn bsrr(&self) -> Reg<WO> {
+                Reg::at(self.base | offsets::gpio::BSRR)
+            }
+            // pins 0 - 7 are found in afrl, 8 - 15 in afrh
+            pub const fn afr(&self, pin: u8) -> Reg<RW> {
+                match pin {
+                    0..=7 => Reg::at(self.base | offsets::gpio::AFRL),
+                    _ => Reg::at(self.base | offsets::gpio::AFRH),
+                }
+            }
+        }
+
+        // moder, ospeedr and pupdr use two bits per pin
+        pub const fn mode(pin: u8) -> Field {
+            Field::new(pin as u32 * 2, 2)
+        }
+        pub const fn pin(pin: u8) -> Field {
+            Field::bit(pin as u32)
+        }
+        // bsrr
+        pub const fn set(pin: u8) -> Field {
+            Field::bit(pin as u32)
+        }
+        pub const fn reset(pin: u8) -> Field {
+            Field::bit(16 + pin as u32)
+        }
+        pub const fn af(pin: u8) -> Field {
+            Field::new((pin as u32 % 8) * 4, 4)
+        }
+    }
+
+    pub mod syscfg {
+        use crate::generic::platform::{adresses, offsets};
+        use crate::generic::reg::{Field, Reg, RW};
+
+        // exticr1 - exticr4 select the port of four lines each
+        pub const fn exticr(line: u8) -> Reg<RW> {
+            Reg::at(adresses::SYSCFG | (offsets::syscfg::EXTICR1 + (line as u32 / 4) * 4))
+        }
+        // 0 for port a, 1 for port b and so on
+        pub const fn exti_port(line: u8) -> Field {
+            Field::new((line as u32 % 4) * 4, 4)
+        }
+    }
+
+    pub mod exti {
+        use crate::generic::platform::{adresses, offsets};
+        use crate::generic::reg::{Field, Reg, RW};
+
+        // interrupt mask, a set bit lets the line raise its interrupt
+        pub const IMR: Reg<RW> = Reg::at(adresses::EXTI | offsets::exti::IMR);
+        pub const RTSR: Reg<RW> = Reg::at(adresses::EXTI | offsets::exti::RTSR);
+        pub const FTSR: Reg<RW> = Reg::at(adresses::EXTI | offsets::exti::FTSR);
+        // pending lines, cleared by writing 1
+        pub const PR: Reg<RW> = Reg::at(adresses::EXTI | offsets::exti::PR);
+
+        pub const fn line(line: u8) -> Field {
+            Field::bit(line as u32)
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//!
+//! Peripherals of the nRF51822 (bbc micro:bit), page numbers refer to
+//! the nRF51 series reference manual v3.0. The chip shares none of the
+//! stm32 peripherals, so it replaces the whole `platform` module.
+//! A peripheral gets started and stopped by writing 1 to a task
+//! register and signals through event registers, which have to be
+//! cleared by writing 0.
+//!
+pub mod adresses {
+    // memory map, p 14
+    pub const FLASH: u32 = 0x0000_0000;
+    pub const SRAM: u32 = 0x2000_0000;
+
+    pub const UART0: u32 = 0x4000_2000;
+    pub const TIMER0: u32 = 0x4000_8000;
+    pub const GPIO: u32 = 0x5000_0000;
+}
+
+pub mod offsets {
+    // p 154
+    pub mod uart {
+        pub const TASKS_STARTTX: u32 = 0x008;
+        pub const TASKS_STOPTX: u32 = 0x00C;
+        pub const EVENTS_TXDRDY: u32 = 0x11C;
+        pub const ENABLE: u32 = 0x500;
+        pub const PSELTXD: u32 = 0x50C;
+        pub const TXD: u32 = 0x51C;
+        pub const BAUDRATE: u32 = 0x524;
+    }
+    // p 100
+    pub mod timer {
+        pub const TASKS_START: u32 = 0x000;
+        pub const TASKS_STOP: u32 = 0x004;
+        pub const TASKS_CLEAR: u32 = 0x00C;
+        pub const EVENTS_COMPARE0: u32 = 0x140;
+        pub const SHORTS: u32 = 0x200;
+        pub const INTENSET: u32 = 0x304;
+        pub const MODE: u32 = 0x504;
+        pub const BITMODE: u32 = 0x508;
+        pub const PRESCALER: u32 = 0x510;
+        pub const CC0: u32 = 0x540;
+    }
+    // p 58
+    pub mod gpio {
+        pub const OUTSET: u32 = 0x508;
+        pub const OUTCLR: u32 = 0x50C;
+        pub const DIRSET: u32 = 0x518;
+    }
+}
+
+// position of the peripheral interrupts within the nvic, p 22
+pub mod irqn {
+    pub const COUNT: usize = 32;
+
+    pub const UART0: usize = 2;
+    pub const TIMER0: usize = 8;
+    // timer0 counts the sleep time in place of the stm32 timer 3
+    pub const TIM3: usize = TIMER0;
+}
+
+// typed registers of the peripherals, see generic::reg
+pub mod registers {
+    pub mod uart {
+        use crate::generic::platform::{adresses, offsets};
+        use crate::generic::reg::{Reg, RW, WO};
+
+        pub const STARTTX: Reg<WO> = Reg::at(adresses::UART0 | offsets::uart::TASKS_STARTTX);
+        pub const STOPTX: Reg<WO> = Reg::at(adresses::UART0 | offsets::uart::TASKS_STOPTX);
+        pub const TXDRDY: Reg<RW> = Reg::at(adresses::UART0 | offsets::uart::EVENTS_TXDRDY);
+        pub const ENABLE: Reg<RW> = Reg::at(adresses::UART0 | offsets::uart::ENABLE);
+        pub const PSELTXD: Reg<RW> = Reg::at(adresses::UART0 | offsets::uart::PSELTXD);
+        pub const TXD: Reg<WO> = Reg::at(adresses::UART0 | offsets::uart::TXD);
+        pub const BAUDRATE: Reg<RW> = Reg::at(adresses::UART0 | offsets::uart::BAUDRATE);
+
+        // enable
+        pub const ENABLED: u32 = 4;
+        // baudrate, p 160
+        pub const BAUD9600: u32 = 0x0027_5000;
+        pub const BAUD115200: u32 = 0x01D7_E000;
+    }
+
+    pub mod timer {
+        use crate::generic::platform::{adresses, offsets};
+        use crate::generic::reg::{Field, Reg, RW, WO};
+
+        pub const START: Reg<WO> = Reg::at(adresses::TIMER0 | offsets::timer::TASKS_START);
+        pub const STOP: Reg<WO> = Reg::at(adresses::TIMER0 | offsets::timer::TASKS_STOP);
+        pub const CLEAR: Reg<WO> = Reg::at(adresses::TIMER0 | offsets::timer::TASKS_CLEAR);
+        pub const COMPARE0: Reg<RW> = Reg::at(adresses::TIMER0 | offsets::timer::EVENTS_COMPARE0);
+        pub const SHORTS: Reg<RW> = Reg::at(adresses::TIMER0 | offsets::timer::SHORTS);
+        pub const INTENSET: Reg<RW> = Reg::at(adresses::TIMER0 | offsets::timer::INTENSET);
+        pub const MODE: Reg<RW> = Reg::at(adresses::TIMER0 | offsets::timer::MODE);
+        pub const BITMODE: Reg<RW> = Reg::at(adresses::TIMER0 | offsets::timer::BITMODE);
+        pub const PRESCALER: Reg<RW> = Reg::at(adresses::TIMER0 | offsets::timer::PRESCALER);
+        pub const CC0: Reg<RW> = Reg::at(adresses::TIMER0 | offsets::timer::CC0);
+
+        // shorts, the counter stops and restarts from 0 on compare 0
+        pub const COMPARE0_CLEAR: Field = Field::bit(0);
+        pub const COMPARE0_STOP: Field = Field::bit(8);
+        // intenset
+        pub const COMPARE0_INT: Field = Field::bit(16);
+        // f_timer = 16 mhz / 2^prescaler
+        pub const SCALE: Field = Field::new(0, 4);
+        // mode
+        pub const TIMER_MODE: u32 = 0;
+        // bitmode
+        pub const BITS_16: u32 = 0;
+    }
+
+    pub mod gpio {
+        use crate::generic::platform::{adresses, offsets};
+        use crate::generic::reg::{Field, Reg, WO};
+
+        // writing a one changes the pin, zeros are ignored
+        pub const OUTSET: Reg<WO> = Reg::at(adresses::GPIO | offsets::gpio::OUTSET);
+        pub const OUTCLR: Reg<WO> = Reg::at(adresses::GPIO | offsets::gpio::OUTCLR);
+        pub const DIRSET: Reg<WO> = Reg::at(adresses::GPIO | offsets::gpio::DIRSET);
+
+        pub const fn pin(pin: u8) -> Field {
+            Field::bit(pin as u32)
+        }
+    }
+}
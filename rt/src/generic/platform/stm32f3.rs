@@ -0,0 +1,225 @@
+//!
+//! STM32F303, page numbers refer to the reference manual rm0316
+//! unless they point to the datasheet of the stm32f303vc.
+//!
+pub mod adresses {
+    pub const USART1_BASEADRESS: u32 = 0x4001_3800;
+
+    // p52
+    pub const GPIOA: u32 = 0x4800_0000;
+    pub const GPIOB: u32 = 0x4800_0400;
+    pub const GPIOC: u32 = 0x4800_0800;
+    pub const GPIOD: u32 = 0x4800_0C00;
+    pub const GPIOE: u32 = 0x4800_1000;
+    pub const GPIOF: u32 = 0x4800_1400;
+
+    // manuel page 55
+    pub const RCC: u32 = 0x4002_1000;
+
+    pub const SYSCFG: u32 = 0x4001_0000;
+    pub const EXTI: u32 = 0x4001_0400;
+
+    // tim2 and tim3 are found at the same place on every chip
+    pub const TIM4_BASEADRESS: u32 = 0x4000_0800;
+    pub const TIM6_BASEADRESS: u32 = 0x4000_1000;
+    pub const TIM7_BASEADRESS: u32 = 0x4000_1400;
+    pub const TIM1_BASEADRESS: u32 = 0x4001_2C00;
+    pub const TIM8_BASEADRESS: u32 = 0x4001_3400;
+    pub const TIM15_BASEADRESS: u32 = 0x4001_4000;
+    pub const TIM16_BASEADRESS: u32 = 0x4001_4400;
+    pub const TIM17_BASEADRESS: u32 = 0x4001_4800;
+}
+
+pub mod offsets {
+    pub mod rcc {
+        pub const RCC_APB2RSTR: u32 = 0x0C;
+        pub const RCC_APB1RSTR: u32 = 0x10;
+        pub const RCC_AHBENR: u32 = 0x14;
+        pub const RCC_APB2ENR: u32 = 0x18;
+        pub const RCC_APB1ENR: u32 = 0x1C;
+    }
+    pub mod usart1 {
+        pub const CR1: u32 = 0x00;
+        pub const TDR: u32 = 0x28;
+        pub const ISR: u32 = 0x1C;
+        pub const BRR: u32 = 0x0C;
+    }
+}
+
+// position of the peripheral interrupts within the nvic, p 288
+pub mod irqn {
+    pub const COUNT: usize = 82;
+
+    // lines 0 - 4 have an interrupt of their own, the others share them
+    pub const EXTI0: usize = 6;
+    pub const EXTI1: usize = 7;
+    pub const EXTI2: usize = 8;
+    pub const EXTI3: usize = 9;
+    pub const EXTI4: usize = 10;
+    pub const EXTI9_5: usize = 23;
+    pub const EXTI15_10: usize = 40;
+
+    // tim1 shares its interrupts with tim15 - tim17
+    pub const TIM1_BRK_TIM15: usize = 24;
+    pub const TIM1_UP_TIM16: usize = 25;
+    pub const TIM1_TRG_COM_TIM17: usize = 26;
+    pub const TIM1_CC: usize = 27;
+    pub const TIM2: usize = 28;
+    pub const TIM3: usize = 29;
+    pub const TIM4: usize = 30;
+    pub const USART1: usize = 37;
+    pub const TIM8_UP: usize = 44;
+    pub const TIM8_CC: usize = 46;
+    pub const TIM6_DAC: usize = 54;
+    pub const TIM7: usize = 55;
+}
+
+pub mod fields {
+    pub mod rcc {
+        use crate::generic::reg::Field;
+
+        // ahbenr, iopaen follows iopben and so on, p 148
+        pub const fn iopen(port: u32) -> Field {
+            Field::bit(17 + port)
+        }
+        // apb2enr
+        pub const SYSCFGEN: Field = Field::bit(0);
+        pub const USART1EN: Field = Field::bit(14);
+    }
+    pub mod usart {
+        use crate::generic::reg::Field;
+
+        // cr1
+        pub const UE: Field = Field::bit(0);
+    }
+}
+
+// general purpose and advanced timers, tim1 - tim17. the enable and
+// reset bits share their position within the enr and rstr registers.
+pub mod timers {
+    use super::adresses;
+    use super::irqn;
+    use crate::generic::platform::adresses::{TIM2_BASEADRESS, TIM3_BASEADRESS};
+    use crate::generic::platform::registers::tim::{Bus, Tim};
+
+    pub const TIM1: Tim = Tim {
+        base: adresses::TIM1_BASEADRESS,
+        bus: Bus::Apb2,
+        bit: 11,
+        irq: irqn::TIM1_UP_TIM16,
+        cc_irq: irqn::TIM1_CC,
+        width: 16,
+        channels: 4,
+        advanced: true,
+    };
+    pub const TIM2: Tim = Tim {
+        base: TIM2_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 0,
+        irq: irqn::TIM2,
+        cc_irq: irqn::TIM2,
+        width: 32,
+        channels: 4,
+        advanced: false,
+    };
+    pub const TIM3: Tim = Tim {
+        base: TIM3_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 1,
+        irq: irqn::TIM3,
+        cc_irq: irqn::TIM3,
+        width: 16,
+        channels: 4,
+        advanced: false,
+    };
+    pub const TIM4: Tim = Tim {
+        base: adresses::TIM4_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 2,
+        irq: irqn::TIM4,
+        cc_irq: irqn::TIM4,
+        width: 16,
+        channels: 4,
+        advanced: false,
+    };
+    // basic timers without channels
+    pub const TIM6: Tim = Tim {
+        base: adresses::TIM6_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 4,
+        irq: irqn::TIM6_DAC,
+        cc_irq: irqn::TIM6_DAC,
+        width: 16,
+        channels: 0,
+        advanced: false,
+    };
+    pub const TIM7: Tim = Tim {
+        base: adresses::TIM7_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 5,
+        irq: irqn::TIM7,
+        cc_irq: irqn::TIM7,
+        width: 16,
+        channels: 0,
+        advanced: false,
+    };
+    pub const TIM8: Tim = Tim {
+        base: adresses::TIM8_BASEADRESS,
+        bus: Bus::Apb2,
+        bit: 13,
+        irq: irqn::TIM8_UP,
+        cc_irq: irqn::TIM8_CC,
+        width: 16,
+        channels: 4,
+        advanced: true,
+    };
+    pub const TIM15: Tim = Tim {
+        base: adresses::TIM15_BASEADRESS,
+        bus: Bus::Apb2,
+        bit: 16,
+        irq: irqn::TIM1_BRK_TIM15,
+        cc_irq: irqn::TIM1_BRK_TIM15,
+        width: 16,
+        channels: 2,
+        advanced: false,
+    };
+    pub const TIM16: Tim = Tim {
+        base: adresses::TIM16_BASEADRESS,
+        bus: Bus::Apb2,
+        bit: 17,
+        irq: irqn::TIM1_UP_TIM16,
+        cc_irq: irqn::TIM1_UP_TIM16,
+        width: 16,
+        channels: 1,
+        advanced: false,
+    };
+    pub const TIM17: Tim = Tim {
+        base: adresses::TIM17_BASEADRESS,
+        bus: Bus::Apb2,
+        bit: 18,
+        irq: irqn::TIM1_TRG_COM_TIM17,
+        cc_irq: irqn::TIM1_TRG_COM_TIM17,
+        width: 16,
+        channels: 1,
+        advanced: false,
+    };
+
+    ///
+    /// The timer with the given number, e.g. 3 for tim3.
+    ///
+    pub fn instance(number: u32) -> Option<Tim> {
+        match number {
+            1 => Some(TIM1),
+            2 => Some(TIM2),
+            3 => Some(TIM3),
+            4 => Some(TIM4),
+            6 => Some(TIM6),
+            7 => Some(TIM7),
+            8 => Some(TIM8),
+            15 => Some(TIM15),
+            16 => Some(TIM16),
+            17 => Some(TIM17),
+            _ => None,
+        }
+    }
+}
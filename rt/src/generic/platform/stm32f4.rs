@@ -0,0 +1,280 @@
+//!
+//! STM32F405 / STM32F407, page numbers refer to the reference
+//! manual rm0090 unless they point to the datasheet.
+//! qemu emulates an stm32f405 without gpio and rcc,
+//! accesses to them get ignored there.
+//!
+pub mod adresses {
+    // datasheet p 64
+    pub const USART1_BASEADRESS: u32 = 0x4001_1000;
+
+    pub const GPIOA: u32 = 0x4002_0000;
+    pub const GPIOB: u32 = 0x4002_0400;
+    pub const GPIOC: u32 = 0x4002_0800;
+    pub const GPIOD: u32 = 0x4002_0C00;
+    pub const GPIOE: u32 = 0x4002_1000;
+    pub const GPIOF: u32 = 0x4002_1400;
+
+    pub const RCC: u32 = 0x4002_3800;
+
+    pub const SYSCFG: u32 = 0x4001_3800;
+    pub const EXTI: u32 = 0x4001_3C00;
+
+    // tim2 and tim3 are found at the same place on every chip
+    pub const TIM4_BASEADRESS: u32 = 0x4000_0800;
+    pub const TIM5_BASEADRESS: u32 = 0x4000_0C00;
+    pub const TIM6_BASEADRESS: u32 = 0x4000_1000;
+    pub const TIM7_BASEADRESS: u32 = 0x4000_1400;
+    pub const TIM12_BASEADRESS: u32 = 0x4000_1800;
+    pub const TIM13_BASEADRESS: u32 = 0x4000_1C00;
+    pub const TIM14_BASEADRESS: u32 = 0x4000_2000;
+    pub const TIM1_BASEADRESS: u32 = 0x4001_0000;
+    pub const TIM8_BASEADRESS: u32 = 0x4001_0400;
+    pub const TIM9_BASEADRESS: u32 = 0x4001_4000;
+    pub const TIM10_BASEADRESS: u32 = 0x4001_4400;
+    pub const TIM11_BASEADRESS: u32 = 0x4001_4800;
+}
+
+pub mod offsets {
+    // the f4 calls ahbenr ahb1enr, p 265
+    pub mod rcc {
+        pub const RCC_APB1RSTR: u32 = 0x20;
+        pub const RCC_APB2RSTR: u32 = 0x24;
+        pub const RCC_AHBENR: u32 = 0x30;
+        pub const RCC_APB1ENR: u32 = 0x40;
+        pub const RCC_APB2ENR: u32 = 0x44;
+    }
+    // the f4 has a combined data register dr and a status register sr
+    // in place of tdr and isr, the driver uses them the same way. p 1007
+    pub mod usart1 {
+        pub const ISR: u32 = 0x00;
+        pub const TDR: u32 = 0x04;
+        pub const BRR: u32 = 0x08;
+        pub const CR1: u32 = 0x0C;
+    }
+}
+
+// position of the peripheral interrupts within the nvic, p 372
+pub mod irqn {
+    pub const COUNT: usize = 82;
+
+    // lines 0 - 4 have an interrupt of their own, the others share them
+    pub const EXTI0: usize = 6;
+    pub const EXTI1: usize = 7;
+    pub const EXTI2: usize = 8;
+    pub const EXTI3: usize = 9;
+    pub const EXTI4: usize = 10;
+    pub const EXTI9_5: usize = 23;
+    pub const EXTI15_10: usize = 40;
+
+    // tim1 and tim8 share their interrupts with tim9 - tim14
+    pub const TIM1_BRK_TIM9: usize = 24;
+    pub const TIM1_UP_TIM10: usize = 25;
+    pub const TIM1_TRG_COM_TIM11: usize = 26;
+    pub const TIM1_CC: usize = 27;
+    pub const TIM2: usize = 28;
+    pub const TIM3: usize = 29;
+    pub const TIM4: usize = 30;
+    pub const USART1: usize = 37;
+    pub const TIM8_BRK_TIM12: usize = 43;
+    pub const TIM8_UP_TIM13: usize = 44;
+    pub const TIM8_TRG_COM_TIM14: usize = 45;
+    pub const TIM8_CC: usize = 46;
+    pub const TIM5: usize = 50;
+    pub const TIM6_DAC: usize = 54;
+    pub const TIM7: usize = 55;
+}
+
+pub mod fields {
+    pub mod rcc {
+        use crate::generic::reg::Field;
+
+        // ahb1enr, gpioaen follows gpioben and so on, p 180
+        pub const fn iopen(port: u32) -> Field {
+            Field::bit(port)
+        }
+        // apb2enr
+        pub const SYSCFGEN: Field = Field::bit(14);
+        pub const USART1EN: Field = Field::bit(4);
+    }
+    pub mod usart {
+        use crate::generic::reg::Field;
+
+        // cr1
+        pub const UE: Field = Field::bit(13);
+    }
+}
+
+// general purpose and advanced timers, tim1 - tim14. the enable and
+// reset bits share their position within the enr and rstr registers.
+pub mod timers {
+    use super::adresses;
+    use super::irqn;
+    use crate::generic::platform::adresses::{TIM2_BASEADRESS, TIM3_BASEADRESS};
+    use crate::generic::platform::registers::tim::{Bus, Tim};
+
+    pub const TIM1: Tim = Tim {
+        base: adresses::TIM1_BASEADRESS,
+        bus: Bus::Apb2,
+        bit: 0,
+        irq: irqn::TIM1_UP_TIM10,
+        cc_irq: irqn::TIM1_CC,
+        width: 16,
+        channels: 4,
+        advanced: true,
+    };
+    pub const TIM2: Tim = Tim {
+        base: TIM2_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 0,
+        irq: irqn::TIM2,
+        cc_irq: irqn::TIM2,
+        width: 32,
+        channels: 4,
+        advanced: false,
+    };
+    pub const TIM3: Tim = Tim {
+        base: TIM3_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 1,
+        irq: irqn::TIM3,
+        cc_irq: irqn::TIM3,
+        width: 16,
+        channels: 4,
+        advanced: false,
+    };
+    pub const TIM4: Tim = Tim {
+        base: adresses::TIM4_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 2,
+        irq: irqn::TIM4,
+        cc_irq: irqn::TIM4,
+        width: 16,
+        channels: 4,
+        advanced: false,
+    };
+    pub const TIM5: Tim = Tim {
+        base: adresses::TIM5_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 3,
+        irq: irqn::TIM5,
+        cc_irq: irqn::TIM5,
+        width: 32,
+        channels: 4,
+        advanced: false,
+    };
+    // basic timers without channels
+    pub const TIM6: Tim = Tim {
+        base: adresses::TIM6_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 4,
+        irq: irqn::TIM6_DAC,
+        cc_irq: irqn::TIM6_DAC,
+        width: 16,
+        channels: 0,
+        advanced: false,
+    };
+    pub const TIM7: Tim = Tim {
+        base: adresses::TIM7_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 5,
+        irq: irqn::TIM7,
+        cc_irq: irqn::TIM7,
+        width: 16,
+        channels: 0,
+        advanced: false,
+    };
+    pub const TIM8: Tim = Tim {
+        base: adresses::TIM8_BASEADRESS,
+        bus: Bus::Apb2,
+        bit: 1,
+        irq: irqn::TIM8_UP_TIM13,
+        cc_irq: irqn::TIM8_CC,
+        width: 16,
+        channels: 4,
+        advanced: true,
+    };
+    pub const TIM9: Tim = Tim {
+        base: adresses::TIM9_BASEADRESS,
+        bus: Bus::Apb2,
+        bit: 16,
+        irq: irqn::TIM1_BRK_TIM9,
+        cc_irq: irqn::TIM1_BRK_TIM9,
+        width: 16,
+        channels: 2,
+        advanced: false,
+    };
+    pub const TIM10: Tim = Tim {
+        base: adresses::TIM10_BASEADRESS,
+        bus: Bus::Apb2,
+        bit: 17,
+        irq: irqn::TIM1_UP_TIM10,
+        cc_irq: irqn::TIM1_UP_TIM10,
+        width: 16,
+        channels: 1,
+        advanced: false,
+    };
+    pub const TIM11: Tim = Tim {
+        base: adresses::TIM11_BASEADRESS,
+        bus: Bus::Apb2,
+        bit: 18,
+        irq: irqn::TIM1_TRG_COM_TIM11,
+        cc_irq: irqn::TIM1_TRG_COM_TIM11,
+        width: 16,
+        channels: 1,
+        advanced: false,
+    };
+    pub const TIM12: Tim = Tim {
+        base: adresses::TIM12_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 6,
+        irq: irqn::TIM8_BRK_TIM12,
+        cc_irq: irqn::TIM8_BRK_TIM12,
+        width: 16,
+        channels: 2,
+        advanced: false,
+    };
+    pub const TIM13: Tim = Tim {
+        base: adresses::TIM13_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 7,
+        irq: irqn::TIM8_UP_TIM13,
+        cc_irq: irqn::TIM8_UP_TIM13,
+        width: 16,
+        channels: 1,
+        advanced: false,
+    };
+    pub const TIM14: Tim = Tim {
+        base: adresses::TIM14_BASEADRESS,
+        bus: Bus::Apb1,
+        bit: 8,
+        irq: irqn::TIM8_TRG_COM_TIM14,
+        cc_irq: irqn::TIM8_TRG_COM_TIM14,
+        width: 16,
+        channels: 1,
+        advanced: false,
+    };
+
+    ///
+    /// The timer with the given number, e.g. 3 for tim3.
+    ///
+    pub fn instance(number: u32) -> Option<Tim> {
+        match number {
+            1 => Some(TIM1),
+            2 => Some(TIM2),
+            3 => Some(TIM3),
+            4 => Some(TIM4),
+            5 => Some(TIM5),
+            6 => Some(TIM6),
+            7 => Some(TIM7),
+            8 => Some(TIM8),
+            9 => Some(TIM9),
+            10 => Some(TIM10),
+            11 => Some(TIM11),
+            12 => Some(TIM12),
+            13 => Some(TIM13),
+            14 => Some(TIM14),
+            _ => None,
+        }
+    }
+}
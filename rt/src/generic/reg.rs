@@ -0,0 +1,123 @@
+//!
+//! Typed access to memory mapped registers. A register knows its
+//! adress and whether it may be read and/or written, fields name
+//! a range of bits within it. Every access is volatile, so the
+//! compiler neither merges nor drops reads and writes.
+//! The accesses go through `hal::Backend`.
+//!
+use crate::hal::{Backend, Hal};
+use core::marker::PhantomData;
+
+/// Read and write access.
+pub struct RW;
+/// Read only access, e.g. status registers.
+pub struct RO;
+/// Write only access, e.g. data or set/clear registers.
+pub struct WO;
+
+pub trait Readable {}
+pub trait Writable {}
+
+impl Readable for RW {}
+impl Readable for RO {}
+impl Writable for RW {}
+impl Writable for WO {}
+
+///
+/// A range of `width` bits starting at bit `offset`.
+///
+#[derive(Clone, Copy)]
+pub struct Field {
+    pub offset: u32,
+    pub width: u32,
+}
+
+impl Field {
+    pub const fn new(offset: u32, width: u32) -> Field {
+        Field { offset, width }
+    }
+
+    pub const fn bit(offset: u32) -> Field {
+        Field { offset, width: 1 }
+    }
+
+    ///
+    /// The bits of the field at their position within the register.
+    ///
+    pub const fn mask(&self) -> u32 {
+        let bits = if self.width >= 32 { u32::MAX } else { (0b1 << self.width) - 1 };
+        bits << self.offset
+    }
+
+    ///
+    /// Shifts `value` to the position of the field, surplus bits get cut off.
+    ///
+    pub const fn value(&self, value: u32) -> u32 {
+        (value << self.offset) & self.mask()
+    }
+}
+
+pub struct Reg<A> {
+    adress: u32,
+    access: PhantomData<A>,
+}
+
+impl<A> Reg<A> {
+    // registers exist at the adresses of `platform` and `cpu` only,
+    // no code outside of the kernel creates one of its own
+    pub(crate) const fn at(adress: u32) -> Reg<A> {
+        Reg {
+            adress,
+            access: PhantomData,
+        }
+    }
+
+    pub const fn adress(&self) -> u32 {
+        self.adress
+    }
+}
+
+impl<A: Readable> Reg<A> {
+    pub fn read(&self) -> u32 {
+        Backend::read(self.adress)
+    }
+
+    pub fn read_field(&self, field: Field) -> u32 {
+        (self.read() & field.mask()) >> field.offset
+    }
+
+    pub fn is_set(&self, field: Field) -> bool {
+        self.read() & field.mask() != 0
+    }
+}
+
+impl<A: Writable> Reg<A> {
+    ///
+    /// Replaces the whole content of the register.
+    ///
+    pub fn write(&self, value: u32) {
+        Backend::write(self.adress, value);
+    }
+}
+
+impl<A: Readable + Writable> Reg<A> {
+    pub fn modify(&self, f: impl FnOnce(u32) -> u32) {
+        self.write(f(self.read()));
+    }
+
+    ///
+    /// Replaces the bits of `field` with `value`, the remaining
+    /// bits of the register keep their content.
+    ///
+    pub fn write_field(&self, field: Field, value: u32) {
+        self.modify(|r| (r & !field.mask()) | field.value(value));
+    }
+
+    pub fn set(&self, field: Field) {
+        self.modify(|r| r | field.mask());
+    }
+
+    pub fn clear(&self, field: Field) {
+        self.modify(|r| r & !field.mask());
+    }
+}
@@ -0,0 +1,93 @@
+//!
+//! Backend of every register access. On the target a register adress
+//! gets dereferenced, on a host the access ends up in a fake peripheral
+//! map instead, so drivers and the scheduler run within `cargo test`.
+//! The hosted port emulates the few peripherals the kernel needs.
+//!
+pub trait Hal {
+    fn read(adress: u32) -> u32;
+    fn write(adress: u32, value: u32);
+}
+
+///
+/// Memory mapped io, the registers live at their adress.
+///
+pub struct Mmio;
+
+impl Hal for Mmio {
+    fn read(adress: u32) -> u32 {
+        unsafe { core::ptr::read_volatile(adress as *const u32) }
+    }
+
+    fn write(adress: u32, value: u32) {
+        unsafe {
+            core::ptr::write_volatile(adress as *mut u32, value);
+        }
+    }
+}
+
+#[cfg(target_os = "none")]
+pub type Backend = Mmio;
+#[cfg(all(not(target_os = "none"), not(feature = "hosted")))]
+pub type Backend = fake::FakePeripherals;
+#[cfg(all(not(target_os = "none"), feature = "hosted"))]
+pub type Backend = crate::generic::hosted::Peripherals;
+
+///
+/// In-memory register map for host builds. Every test thread gets
+/// its own map, registers which were never written read as 0.
+/// The hardware does not react to anything, so status bits a driver
+/// polls on have to be preset with `set`.
+///
+#[cfg(not(target_os = "none"))]
+pub mod fake {
+    use super::Hal;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::vec::Vec;
+
+    thread_local! {
+        static REGISTERS: RefCell<HashMap<u32, u32>> = RefCell::new(HashMap::new());
+        // every write in order of occurrence
+        static WRITES: RefCell<Vec<(u32, u32)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    pub struct FakePeripherals;
+
+    impl Hal for FakePeripherals {
+        fn read(adress: u32) -> u32 {
+            get(adress)
+        }
+
+        fn write(adress: u32, value: u32) {
+            set(adress, value);
+            WRITES.with(|w| w.borrow_mut().push((adress, value)));
+        }
+    }
+
+    ///
+    /// Sets a register without recording the write.
+    ///
+    pub fn set(adress: u32, value: u32) {
+        REGISTERS.with(|r| r.borrow_mut().insert(adress, value));
+    }
+
+    pub fn get(adress: u32) -> u32 {
+        REGISTERS.with(|r| r.borrow().get(&adress).copied().unwrap_or(0))
+    }
+
+    ///
+    /// Values written to `adress` by the drivers, oldest first.
+    ///
+    pub fn writes_to(adress: u32) -> Vec<u32> {
+        WRITES.with(|w| w.borrow().iter().filter(|(a, _)| *a == adress).map(|(_, v)| *v).collect())
+    }
+
+    ///
+    /// Clears all registers and the recorded writes.
+    ///
+    pub fn reset() {
+        REGISTERS.with(|r| r.borrow_mut().clear());
+        WRITES.with(|w| w.borrow_mut().clear());
+    }
+}
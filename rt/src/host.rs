@@ -0,0 +1,76 @@
+//!
+//! Stand-ins for the routines of `asm/dispatch_mng.s` on a host.
+//! There is neither a process stack nor an exception model, so the
+//! context switch routines do nothing and a trap calls the handler
+//! of the kernel directly, as if the task was running privileged.
+//! The hosted port replaces the routines which switch between the
+//! tasks, see `generic::hosted`.
+//!
+#[cfg(not(feature = "hosted"))]
+use crate::sys::call_api::TrapMeta;
+use std::cell::Cell;
+
+thread_local! {
+    static PSP: Cell<u32> = const { Cell::new(0) };
+}
+
+#[no_mangle]
+pub extern "C" fn __get_current_psp() -> u32 {
+    PSP.with(|p| p.get())
+}
+
+#[no_mangle]
+pub extern "C" fn __write_psp(addr: u32) {
+    PSP.with(|p| p.set(addr));
+}
+
+#[no_mangle]
+pub extern "C" fn __save_process_context(_exc_return: u32) {}
+
+#[cfg(not(feature = "hosted"))]
+#[no_mangle]
+pub extern "C" fn __load_process_context(addr: u32, _exc_return: u32) {
+    __write_psp(addr);
+}
+
+#[no_mangle]
+pub extern "C" fn __get_r0() -> u32 {
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn __disable_interrupts() {}
+
+#[no_mangle]
+pub extern "C" fn __enter_critical() -> u32 {
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn __exit_critical(_primask: u32) {}
+
+#[no_mangle]
+pub extern "C" fn __in_unprivileged_thread() -> u32 {
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn __drop_privileges() {}
+
+#[cfg(not(feature = "hosted"))]
+#[no_mangle]
+pub extern "C" fn __wait_for_interrupt() {}
+
+#[no_mangle]
+pub extern "C" fn __data_barrier() {}
+
+#[no_mangle]
+pub extern "C" fn __semihosting_call(_operation: u32, _parameter: u32) -> u32 {
+    0
+}
+
+#[cfg(not(feature = "hosted"))]
+#[no_mangle]
+pub extern "C" fn __trap(trap_id: &TrapMeta) {
+    crate::handle_trap(trap_id);
+}
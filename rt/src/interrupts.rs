@@ -4,9 +4,10 @@
 //!
 use super::cpu;
 pub mod systick {
-    use super::cpu::{c_adresses, c_offsets, c_bitfields};
-    use crate::mem::memory_handler::{read, write};
-    const FACTOR: u32 = 8000;
+    use super::cpu::c_registers::stk;
+    use super::cpu::system;
+    // cpu cycles per ms
+    const FACTOR: u32 = system::CLOCK / 1000;
 
     #[repr(C)]
     pub struct STK {
@@ -23,26 +24,148 @@ pub mod systick {
             systick_reg.stk_run();
         }
         fn stk_load(&self) {
-            let mut current_register_content = read(c_adresses::STK | c_offsets::stk::LOAD);
-            current_register_content &= !(0x00FF_FFFF);
-            write(c_adresses::STK | c_offsets::stk::LOAD, current_register_content | self.cycles_until_zero);
+            stk::LOAD.write_field(stk::RELOAD, self.cycles_until_zero);
         }
         fn stk_val_clr(&self) {
-            write(c_adresses::STK | c_offsets::stk::VAL, read(c_adresses::STK | c_offsets::stk::VAL) & !(0x00FF_FFFF));
+            stk::VAL.write_field(stk::RELOAD, 0);
         }
         fn stk_run(&self) {
-            let mut existing_val = read(c_adresses::STK | c_offsets::stk::CTRL);
-
-            existing_val &= !(0b111);
-            existing_val |= (0b100) | (0b010) | (0b001);
-
-            write(c_adresses::STK | c_offsets::stk::CTRL, existing_val);
+            stk::CTRL.modify(|r| r | stk::CLKSOURCE.mask() | stk::TICKINT.mask() | stk::ENABLE.mask());
         }
     }
     pub fn disable_systick() {
-        write(c_adresses::STK | c_offsets::stk::CTRL, read(c_adresses::STK | c_offsets::stk::CTRL) & !(c_bitfields::stk::ENABLE));
+        stk::CTRL.clear(stk::ENABLE);
     }
     pub fn enable_systick() {
-        write(c_adresses::STK | c_offsets::stk::CTRL, read(c_adresses::STK | c_offsets::stk::CTRL) | c_bitfields::stk::ENABLE);
+        stk::CTRL.set(stk::ENABLE);
+    }
+}
+
+pub mod nvic {
+    use super::cpu::c_registers::nvic;
+
+    pub fn enable(irq: usize) {
+        nvic::iser(irq).write(nvic::bit(irq));
+    }
+
+    pub fn disable(irq: usize) {
+        nvic::icer(irq).write(nvic::bit(irq));
+    }
+
+    pub fn set_pending(irq: usize) {
+        nvic::ispr(irq).write(nvic::bit(irq));
+    }
+
+    pub fn clear_pending(irq: usize) {
+        nvic::icpr(irq).write(nvic::bit(irq));
+    }
+}
+
+///
+/// RAM based dispatch table for the peripheral interrupts.
+/// Every peripheral entry of the vector table points to the
+/// same dispatcher, which looks up the active interrupt here.
+/// Drivers attach their handlers during runtime, tasks may bind
+/// an interrupt to an event flag and block until it fires.
+///
+pub mod irq {
+    use super::cpu::c_registers::scb;
+    use super::nvic;
+    use crate::generic::platform::irqn;
+    use crate::sched::event;
+
+    // the first 16 vectors are reserved for the cpu exceptions
+    const EXTERNAL_OFFSET: u32 = 16;
+
+    static mut HANDLERS: [Option<fn()>; irqn::COUNT] = [None; irqn::COUNT];
+    static mut EVENTS: [u32; irqn::COUNT] = [0; irqn::COUNT];
+
+    ///
+    /// Attaches `handler` to the interrupt line `irq` and enables
+    /// the line within the nvic. A previously registered handler
+    /// gets replaced.
+    ///
+    pub fn register_irq(irq: usize, handler: fn()) {
+        if irq >= irqn::COUNT {
+            return;
+        }
+        unsafe {
+            HANDLERS[irq] = Some(handler);
+        }
+        nvic::enable(irq);
+    }
+
+    pub fn unregister_irq(irq: usize) {
+        if irq >= irqn::COUNT {
+            return;
+        }
+        nvic::disable(irq);
+        unsafe {
+            HANDLERS[irq] = None;
+        }
+    }
+
+    ///
+    /// Signals the event flags in `mask` whenever `irq` fires.
+    /// Tasks blocked on one of these flags become ready again.
+    ///
+    pub fn bind_event(irq: usize, mask: u32) {
+        if irq >= irqn::COUNT {
+            return;
+        }
+        unsafe {
+            EVENTS[irq] = mask;
+        }
+    }
+
+    ///
+    /// Prepares `irq` for a task which wants to wait on it. If the line
+    /// has no event bound yet, a free event flag gets allocated for it.
+    /// Returns the event mask to wait on or None if the line is invalid,
+    /// belongs to the kernel or all event flags are used up.
+    ///
+    pub fn arm(irq: usize) -> Option<u32> {
+        if irq >= irqn::COUNT {
+            return None;
+        }
+        // lines with a handler belong to a driver of the kernel, like
+        // the sleep timer, the exti lines and the capture timers
+        if unsafe { HANDLERS[irq] }.is_some() {
+            return None;
+        }
+        let mask = match unsafe { EVENTS[irq] } {
+            0 => event::allocate()?,
+            m => m,
+        };
+        bind_event(irq, mask);
+        nvic::enable(irq);
+        Some(mask)
+    }
+
+    ///
+    /// Gets called by every peripheral interrupt. Runs the registered
+    /// handler and signals the bound event flags afterwards.
+    ///
+    pub fn dispatch() {
+        let active = scb::ICSR.read_field(scb::VECTACTIVE);
+        if active < EXTERNAL_OFFSET {
+            return;
+        }
+        let irq = (active - EXTERNAL_OFFSET) as usize;
+        if irq >= irqn::COUNT {
+            return;
+        }
+
+        let (handler, mask) = unsafe { (HANDLERS[irq], EVENTS[irq]) };
+        match handler {
+            Some(h) => h(),
+            // nobody clears the interrupt source, so the line gets masked
+            // until the waiting task re-arms it. otherwise the cpu would
+            // hang in this isr forever.
+            None => nvic::disable(irq),
+        }
+        if mask != 0 {
+            event::signal(mask);
+        }
     }
 }
@@ -3,95 +3,114 @@
 //! Only for runtime purposes, acts as a platform for the kernel
 //! code build on top of it.
 //!
-#![no_main]
-#![no_std]
-#![feature(asm)]
-
+// host builds (`cargo test`) link against std and replace the
+// hardware with the fake peripherals of `hal`
+#![cfg_attr(target_os = "none", no_main)]
+#![cfg_attr(target_os = "none", no_std)]
+// without the kernel feature most of the modules are private
+#![cfg_attr(not(feature = "kernel"), allow(dead_code))]
+// the startup code is only reachable from Reset on the target
+#![cfg_attr(not(target_os = "none"), allow(dead_code))]
+
+// kernel internals, only visible with the "kernel" feature. user
+// crates get compiled without it, so any direct hardware access
+// fails at compile time and has to go through `sys` instead.
+#[cfg(feature = "kernel")]
 pub mod dev;
-pub mod generic;
+#[cfg(not(feature = "kernel"))]
+mod dev;
+#[cfg(feature = "kernel")]
+pub mod fault;
+#[cfg(not(feature = "kernel"))]
+mod fault;
+#[cfg(feature = "kernel")]
+pub mod hal;
+#[cfg(not(feature = "kernel"))]
+mod hal;
+#[cfg(feature = "kernel")]
 pub mod interrupts;
+#[cfg(not(feature = "kernel"))]
+mod interrupts;
+#[cfg(feature = "kernel")]
 pub mod mem;
+#[cfg(not(feature = "kernel"))]
+mod mem;
+#[cfg(feature = "kernel")]
+pub mod mpu;
+#[cfg(not(feature = "kernel"))]
+mod mpu;
+#[cfg(feature = "kernel")]
 pub mod sched;
+#[cfg(not(feature = "kernel"))]
+mod sched;
+// registers, board and cpu layout, hardware access as well
+#[cfg(feature = "kernel")]
+pub mod generic;
+#[cfg(not(feature = "kernel"))]
+mod generic;
+
 pub mod sys;
 
+// stand-ins for the assembler routines
+#[cfg(not(target_os = "none"))]
+mod host;
+
 use crate::sched::{task_control};
+#[cfg(target_os = "none")]
+use core::fmt::Write;
+#[cfg(target_os = "none")]
 use core::panic::PanicInfo;
+use core::mem::size_of;
+#[cfg(target_os = "none")]
 use core::ptr;
-use dev::{tim3, uart::print_from_ptr};
-use generic::platform::{self, adresses, offsets, bitfields};
-use generic::cpu::{self, c_adresses, c_offsets, c_bitfields};
+use dev::{sleep_timer, uart::print_from_ptr};
+use generic::board::{self, timing};
+use generic::platform;
+#[cfg(target_os = "none")]
+use generic::platform::irqn;
+use generic::cpu::{self, c_bitfields::exc_return};
+#[cfg(not(feature = "armv6m"))]
+use generic::cpu::c_registers::fpu;
+use generic::cpu::c_registers::scb;
 use interrupts::systick::{disable_systick, enable_systick};
-use mem::memory_handler::{read, write};
+use sched::event;
 use sched::scheduler::{save_task_context, load_next_task, load_sleeping_task};
 use sys::call_api::TrapMeta;
 
-fn enable_gpio_e_leds() {
-    // see p 54 reg boundaries
-    let gpio_port_e11 = dev::gpio_driver::GpioX::new("E", 11);
-    gpio_port_e11.set_moder(dev::gpio_types::ModerTypes::GeneralPurposeOutputMode);
-    gpio_port_e11.set_otyper(dev::gpio_types::OutputTypes::PushPull);
-    let gpio_port_e14 = dev::gpio_driver::GpioX::new("E", 12);
-    gpio_port_e14.set_moder(dev::gpio_types::ModerTypes::GeneralPurposeOutputMode);
-    gpio_port_e14.set_otyper(dev::gpio_types::OutputTypes::PushPull);
-
-    let gpio_port_e14 = dev::gpio_driver::GpioX::new("E", 14);
-    gpio_port_e14.set_moder(dev::gpio_types::ModerTypes::GeneralPurposeOutputMode);
-    gpio_port_e14.set_otyper(dev::gpio_types::OutputTypes::PushPull);
-
-    let gpio_port_e14 = dev::gpio_driver::GpioX::new("E", 13);
-    gpio_port_e14.set_moder(dev::gpio_types::ModerTypes::GeneralPurposeOutputMode);
-    gpio_port_e14.set_otyper(dev::gpio_types::OutputTypes::PushPull);
+///
+/// Grants access to the fpu and enables the lazy stacking
+/// of its registers on exception entry.
+///
+#[cfg(not(feature = "armv6m"))]
+fn enable_fpu() {
+    scb::CPACR.write_field(scb::CP10_CP11, scb::CP_FULL_ACCESS);
+    fpu::FPCCR.modify(|r| r | fpu::ASPEN.mask() | fpu::LSPEN.mask());
 }
 
-fn setup_clock_system() {
-    // turn on gpio clock
-    // see p 166 -> IOPAEN
-    let rcc_ahbenr = adresses::RCC | offsets::rcc::RCC_AHBENR;
-    write(rcc_ahbenr, 1 << bitfields::rcc::IOPAEN | 1 << bitfields::rcc::IOPEEN);
-
-    // TIM2 and 3 EN -> p 166
-    let rcc_apb1enr: u32 = adresses::RCC | offsets::rcc::RCC_APB1ENR;
-    write(rcc_apb1enr, read(rcc_apb1enr) | 0b11);
-
-    // USART1EN -> p 166
-    let rcc_apb2enr: u32 = adresses::RCC | offsets::rcc::RCC_APB2ENR;
-    write(rcc_apb2enr, read(rcc_apb2enr) | ((0b1 << bitfields::rcc::USART1EN) | bitfields::rcc::SYSCFGEN));
+pub(crate) fn print_banner() {
+    dev::uart::print_str("#########################\n\r");
+    dev::uart::print_str("# WELCOME TO STM32 RTOS #\n\r");
+    dev::uart::print_str("#########################\n\r");
+    dev::uart::print_str(board::BOARD.name);
+    dev::uart::print_str("\n\r");
 }
 
-fn enable_serial_printing() {
-    let gpio_port_a0 = dev::gpio_driver::GpioX::new("A", 9);
-    gpio_port_a0.set_moder(dev::gpio_types::ModerTypes::AlternateFunctionMode);
-    gpio_port_a0.set_otyper(dev::gpio_types::OutputTypes::PushPull);
-    gpio_port_a0.into_af(7);
-
-    let usart1 = dev::uart::new(1, 9600);
-    usart1.enable();
+///
+/// Prepares timer 3, which wakes up sleeping tasks, and attaches
+/// its interrupt. The dispatch table has to be initialized already.
+///
+pub(crate) fn start_sleep_timer() {
+    sleep_timer::init(timing::PRESCALER);
+    interrupts::irq::register_irq(sleep_timer::IRQ, tim3_interrupt);
 }
 
 ///
 /// Mandatory resetfunction at adress 0x08000004.
 /// Gets called after power on the cpu.
 ///
+#[cfg(target_os = "none")]
 #[no_mangle]
 pub unsafe extern "C" fn Reset() -> ! {
-    setup_clock_system();
-    enable_gpio_e_leds();
-    enable_serial_printing();
-
-    tim3::set_prescaler(1000);
-    tim3::set_ug();
-    tim3::clear_uif();
-    tim3::clear_udis();
-    tim3::enable_interrupt();
-
-    // interrupts::systick::STK::set_up_systick(30);
-
-    dev::uart::print_str("#########################\n\r");
-    dev::uart::print_str("# WELCOME TO STM32 RTOS #\n\r");
-    dev::uart::print_str("#########################\n\r");
-
-    // dev::uart::print_dec(123);
-
     extern "C" {
         static mut _sbss: u8;
         static mut _ebss: u8;
@@ -101,15 +120,27 @@ pub unsafe extern "C" fn Reset() -> ! {
         static _sidata: u8;
     }
 
+    // statics first, everything below keeps its state in them. the
+    // crash record lives in .noinit, so it survives this.
     let count = &_ebss as *const u8 as usize - &_sbss as *const u8 as usize;
     ptr::write_bytes(&mut _sbss as *mut u8, 0, count);
 
     let count = &_edata as *const u8 as usize - &_sdata as *const u8 as usize;
     ptr::copy_nonoverlapping(&_sidata as *const u8, &mut _sdata as *mut u8, count);
 
-    let nvic_iser: u32 = 0xE000E100;
-    let existing_value = ptr::read_volatile(nvic_iser as *mut u32);
-    ptr::write_volatile(nvic_iser as *mut u32, existing_value | 0b1 << 29);
+    fault::status::enable_fault_handlers();
+    mpu::mpu_handler::init();
+    #[cfg(not(feature = "armv6m"))]
+    enable_fpu();
+    board::init();
+
+    // interrupts::systick::STK::set_up_systick(30);
+
+    print_banner();
+
+    fault::crash::print_last();
+
+    start_sleep_timer();
 
     extern "Rust" {
         fn main() -> !;
@@ -118,61 +149,89 @@ pub unsafe extern "C" fn Reset() -> ! {
     main()
 }
 
+///
+/// Prints the panic location and message, keeps a crash record
+/// for the next boot and resets the system afterwards.
+///
+#[cfg(target_os = "none")]
 #[panic_handler]
-fn panic(_panic: &PanicInfo<'_>) -> ! {
-    loop {}
+fn panic(info: &PanicInfo<'_>) -> ! {
+    unsafe {
+        __disable_interrupts();
+    }
+    fault::crash::save(info);
+    dev::uart::print_str("\n\r---- !!!KERNEL PANIC!!! ----\n\r");
+    let _ = write!(dev::uart::Writer, "{}\n\r", info);
+    fault::crash::system_reset()
 }
 
+#[cfg(target_os = "none")]
 pub union Vector {
     reserved: u32,
     handler: unsafe extern "C" fn(),
 }
 
+#[cfg(target_os = "none")]
 pub union VectorDivergentFn {
     _reserved: u32,
     handler: unsafe extern "C" fn() -> !,
 }
 
+#[cfg(target_os = "none")]
 extern "C" {
     fn NMI();
     fn HardFault();
+    fn PendSV();
     fn MemManage();
     fn BusFault();
     fn UsageFault();
+}
+
+extern "C" {
     fn __get_r0() -> u32;
-    fn __return_to_user_mode();
+    fn __disable_interrupts();
+    fn __wait_for_interrupt();
+    fn __drop_privileges();
 }
 
 #[no_mangle]
 pub extern "C" fn SysTick() {
     disable_systick();
+    task_control::tick();
     set_pending();
 }
 
 fn set_pending() {
     // Interrupt control and state register, page 225
-    // baseadress: scb, p221 4.4, line 3
-    // offset: 4.4.3, p225
-    let icsr_pendsvset: u32 = c_adresses::SCB | c_offsets::scb::ICSR;
-    let existing_value = read(icsr_pendsvset);
-    write(icsr_pendsvset, existing_value | (0b1 << c_bitfields::icsr::PENDSVSET));
+    scb::ICSR.set(scb::PENDSVSET);
 }
 
 ///
-///
-///
-///
-///
+/// Switches to the next task. Gets entered through `__pendsv_entry`,
+/// which passes the exc_return value of pendsv and returns from the
+/// exception with the one handed back, so the task resumes with the
+/// frame type it got interrupted with.
 ///
 #[no_mangle]
-pub extern "C" fn PendSV() {
+pub extern "C" fn ContextSwitch(exc_return: u32) -> u32 {
     // sched::scheduler::context_switch();
-    save_task_context();
-    load_next_task();
+    save_task_context(exc_return);
+    // a task whose sleep time is over takes precedence
+    // over the round robin order
+    if task_control::take_woken() {
+        load_sleeping_task();
+    } else {
+        load_next_task();
+    }
+    mpu::mpu_handler::switch_to(task_control::current_pid());
     enable_systick();
     unsafe {
-        __return_to_user_mode();
+        // tasks run unprivileged in thread mode. from now on the kernel
+        // thread can not regain the privileges either, but it is not
+        // resumed anymore after the first context switch.
+        __drop_privileges();
     }
+    task_control::current_exc_return()
 }
 
 ///
@@ -186,21 +245,92 @@ pub extern "C" fn PendSV() {
 ///
 #[no_mangle]
 pub extern "C" fn SVCall() {
+    let meta = unsafe { __get_r0() };
+    let pid = task_control::current_pid();
+    if meta % 4 != 0 || !mpu::mpu_handler::task_may_access(pid, meta, size_of::<TrapMeta>() as u32, false) {
+        return;
+    }
+    unsafe {
+        handle_trap(&*(meta as *const TrapMeta));
+    }
+}
+
+///
+/// The kernel accesses the payload with its own privileges, so calls
+/// whose payload lies outside of the regions the mpu opens for the
+/// calling task get refused. On a host the kernel calls itself, see
+/// `host`, and there is nothing to check.
+///
+#[cfg(target_os = "none")]
+fn payload_accessible(trap_meta_info: &TrapMeta) -> bool {
+    use sys::call_api::TrapReason;
+
+    let pid = task_control::current_pid();
+    let adress = trap_meta_info.payload as u32;
+    // words of the payload and whether the kernel writes into it
+    let (words, write) = match trap_meta_info.id {
+        TrapReason::EnableRt
+        | TrapReason::DisableRt
+        | TrapReason::StartMeasurement
+        | TrapReason::YieldTask
+        | TrapReason::TerminateTask => (0, false),
+        TrapReason::Sleep | TrapReason::WaitIrq | TrapReason::Exit => (1, false),
+        TrapReason::StopMeasurement => (1, true),
+        TrapReason::StackUsage | TrapReason::HeapFree => (2, true),
+        TrapReason::HeapAlloc
+        | TrapReason::PoolCreate
+        | TrapReason::PoolAlloc
+        | TrapReason::PoolFree
+        | TrapReason::CaptureWidth
+        | TrapReason::CaptureFrequency
+        | TrapReason::CaptureWait => (3, true),
+        TrapReason::GpioClaim
+        | TrapReason::GpioWrite
+        | TrapReason::GpioRead
+        | TrapReason::GpioToggle
+        | TrapReason::PwmDuty => (4, true),
+        TrapReason::WaitEdge => (5, true),
+        // the string ends with the first nul, which has to be
+        // within the same region
+        TrapReason::WriteStdOut => {
+            let available = mpu::mpu_handler::accessible(pid, adress, false);
+            let string = adress as *const u8;
+            return (0..available as usize).any(|i| unsafe { *string.add(i) } == 0);
+        }
+    };
+    match words {
+        0 => true,
+        _ => adress % 4 == 0 && mpu::mpu_handler::task_may_access(pid, adress, words * 4, write),
+    }
+}
+
+#[cfg(not(target_os = "none"))]
+fn payload_accessible(_trap_meta_info: &TrapMeta) -> bool {
+    true
+}
+
+///
+/// Provides the service requested by `trap_meta_info`. Results
+/// get written back into the payload of the caller.
+///
+pub(crate) fn handle_trap(trap_meta_info: &TrapMeta) {
+    if !payload_accessible(trap_meta_info) {
+        return;
+    }
     unsafe {
-        let trap_meta_info: &mut TrapMeta = &mut *(__get_r0() as *mut TrapMeta);
         match trap_meta_info.id {
             // the calling task passes its desired sleep value within
-            // the trap id. in according to that value the capture compare register of
+            // the trap id. in according to that value the auto reload register of
             // timer 3 gets configured. the task state is set to sleeping, the
             // timer gets startet and when finishing this steps the scheduler
             // gets triggered so load the next running task.
             sys::call_api::TrapReason::Sleep => {
                 let time_to_sleep = trap_meta_info.payload;
-                tim3::set_ccr((*time_to_sleep * 8) as u16);
-                tim3::set_ug();
+                // the update interrupt fires when the counter reaches
+                // the reload value. qemu does not emulate compare interrupts.
+                let ticks = (*time_to_sleep * timing::TICKS_PER_MS).clamp(1, u16::MAX as u32);
                 task_control::mark_self_as_sleeping();
-
-                tim3::start();
+                sleep_timer::start(ticks as u16);
                 set_pending();
             }
             // simply triggers a context switch
@@ -211,20 +341,38 @@ pub extern "C" fn SVCall() {
             // to completed so the scheduler skips this
             // task when searching for runnable task.
             sys::call_api::TrapReason::TerminateTask => {
+                dev::gpio_owner::release_all(task_control::current_pid());
                 task_control::terminate_task();
                 set_pending();
             }
+            // ends the whole system, e.g. after a test. an emulator or
+            // a semihosting debugger exits with the code, a board halts.
+            // only test images may do so, ordinary tasks get refused.
+            sys::call_api::TrapReason::Exit => {
+                #[cfg(feature = "semihosting-exit")]
+                {
+                    let code = *trap_meta_info.payload;
+                    dev::uart::print_str("\n\r---- EXIT ");
+                    dev::uart::print_dec(code);
+                    dev::uart::print_str(" ----\n\r");
+                    dev::semihosting::exit(code);
+                    #[cfg(all(feature = "hosted", not(target_os = "none")))]
+                    generic::hosted::exit(code);
+                    __disable_interrupts();
+                    loop {
+                        __wait_for_interrupt();
+                    }
+                }
+            }
             // disables task scheduling at all so the calling
             // task can finish its critical operation without getting disturbed
             sys::call_api::TrapReason::EnableRt => {
                 disable_systick();
-                return;
             }
             // enables task scheduling. by forgetting the calling task of
             // "enablert" would run forever or hang in terminated state
             sys::call_api::TrapReason::DisableRt => {
                 enable_systick();
-                return;
             }
             // writes a string to standard out, which is UART with baud 9600
             // at PA9 TX / PA10 RX (page 45 stm 32 mapping doc)
@@ -233,8 +381,123 @@ pub extern "C" fn SVCall() {
                 print_from_ptr(str_start as *mut u8);
                 enable_systick();
             }
-            _ => {
-                // not implemented yet...
+            // blocks the calling task until the requested interrupt line
+            // fires. the line gets bound to an event flag on first use.
+            sys::call_api::TrapReason::WaitIrq => {
+                let irq = *trap_meta_info.payload as usize;
+                if let Some(mask) = interrupts::irq::arm(irq) {
+                    if event::wait(mask, 0, core::ptr::null_mut()) {
+                        set_pending();
+                    }
+                }
+            }
+            // time measurement with timer 2, see dev::measurement
+            sys::call_api::TrapReason::StartMeasurement => {
+                dev::measurement::start();
+            }
+            sys::call_api::TrapReason::StopMeasurement => {
+                *(trap_meta_info.payload as *mut u32) = dev::measurement::stop();
+            }
+            // measures the high-water mark of the requested task stack
+            sys::call_api::TrapReason::StackUsage => {
+                let args = trap_meta_info.payload as *mut u32;
+                *args.add(1) = sched::process::stack_usage(*args).unwrap_or(u32::MAX);
+            }
+            // allocations of the global allocator within a task
+            sys::call_api::TrapReason::HeapAlloc => {
+                let args = trap_meta_info.payload as *mut u32;
+                *args.add(2) = mem::heap::kernel_alloc(*args as usize, *args.add(1) as usize) as u32;
+            }
+            sys::call_api::TrapReason::HeapFree => {
+                let args = trap_meta_info.payload as *mut u32;
+                *args.add(1) = mem::heap::kernel_free(*args as *mut u8) as u32;
+            }
+            // fixed size block pools, see mem::pool
+            sys::call_api::TrapReason::PoolCreate => {
+                let args = trap_meta_info.payload as *mut u32;
+                *args.add(2) = mem::pool::create(*args, *args.add(1)).unwrap_or(u32::MAX);
+            }
+            // the block adress gets written by the kernel or, if the
+            // pool is empty, later by the task which frees a block
+            sys::call_api::TrapReason::PoolAlloc => {
+                let args = trap_meta_info.payload as *mut u32;
+                if mem::pool::alloc_or_block(*args, *args.add(1), args.add(2)) {
+                    set_pending();
+                }
+            }
+            sys::call_api::TrapReason::PoolFree => {
+                let args = trap_meta_info.payload as *mut u32;
+                *args.add(2) = mem::pool::free(*args, *args.add(1)) as u32;
+            }
+            // blocks the calling task until an edge on the requested
+            // pin, see dev::exti. the result gets written before blocking.
+            sys::call_api::TrapReason::WaitEdge => {
+                let args = trap_meta_info.payload as *mut u32;
+                let pid = task_control::current_pid();
+                match dev::exti::arm(*args, *args.add(1), *args.add(2), *args.add(3), pid) {
+                    Some(mask) => {
+                        *args.add(4) = 1;
+                        // edges from before the call are stale
+                        event::clear(mask);
+                        if event::wait(mask, 0, core::ptr::null_mut()) {
+                            set_pending();
+                        }
+                    }
+                    None => *args.add(4) = 0,
+                }
+            }
+            // pins owned by tasks, see dev::gpio_owner
+            sys::call_api::TrapReason::GpioClaim => {
+                let args = trap_meta_info.payload as *mut u32;
+                let pid = task_control::current_pid();
+                *args.add(3) = dev::gpio_owner::claim(*args, *args.add(1), *args.add(2), pid) as u32;
+            }
+            sys::call_api::TrapReason::GpioWrite => {
+                let args = trap_meta_info.payload as *mut u32;
+                let pid = task_control::current_pid();
+                *args.add(3) = dev::gpio_owner::write(*args, *args.add(1), *args.add(2) != 0, pid) as u32;
+            }
+            sys::call_api::TrapReason::GpioRead => {
+                let args = trap_meta_info.payload as *mut u32;
+                let pid = task_control::current_pid();
+                *args.add(3) = match dev::gpio_owner::read(*args, *args.add(1), pid) {
+                    Some(level) => level as u32,
+                    None => u32::MAX,
+                };
+            }
+            sys::call_api::TrapReason::GpioToggle => {
+                let args = trap_meta_info.payload as *mut u32;
+                let pid = task_control::current_pid();
+                *args.add(3) = dev::gpio_owner::toggle(*args, *args.add(1), pid) as u32;
+            }
+            // duty cycle of a kernel configured pwm channel, see dev::pwm
+            sys::call_api::TrapReason::PwmDuty => {
+                let args = trap_meta_info.payload as *mut u32;
+                *args.add(3) = dev::pwm::set_duty_of(*args, *args.add(1), *args.add(2)) as u32;
+            }
+            // timer input capture, see dev::capture
+            sys::call_api::TrapReason::CaptureWidth => {
+                let args = trap_meta_info.payload as *mut u32;
+                *args.add(2) = dev::capture::pulse_width(*args, *args.add(1)).unwrap_or(u32::MAX);
+            }
+            sys::call_api::TrapReason::CaptureFrequency => {
+                let args = trap_meta_info.payload as *mut u32;
+                *args.add(2) = dev::capture::frequency(*args, *args.add(1)).unwrap_or(u32::MAX);
+            }
+            // the result gets written before blocking
+            sys::call_api::TrapReason::CaptureWait => {
+                let args = trap_meta_info.payload as *mut u32;
+                match dev::capture::event_of(*args, *args.add(1)) {
+                    Some(mask) => {
+                        *args.add(2) = 1;
+                        // captures from before the call are stale
+                        event::clear(mask);
+                        if event::wait(mask, 0, core::ptr::null_mut()) {
+                            set_pending();
+                        }
+                    }
+                    None => *args.add(2) = 0,
+                }
             }
         }
     }
@@ -242,43 +505,101 @@ pub extern "C" fn SVCall() {
 
 #[no_mangle]
 pub extern "C" fn DefaultExceptionHandler() {
-    loop {}
+    loop {
+        unsafe {
+            __wait_for_interrupt();
+        }
+    }
 }
 
 ///
-/// Interrupt Service Routine when timer 3 cnt register reaches
-/// value in timer 3 capture compare register 1.
+/// Common handler for hard-, memmanage-, bus- and usagefault.
+/// Gets entered through `__fault_entry`, which passes the stacked
+/// exception frame (r0-r3, r12, lr, pc, xpsr) and the exc_return value.
+/// A fault raised by a task only terminates this task, a fault
+/// within the kernel itself halts the system.
+/// # Safety
+/// Only `__fault_entry` calls it, `frame` has to point to the
+/// stacked registers whenever the cpu managed to stack them.
 ///
 #[no_mangle]
-pub extern "C" fn Tim3Interrupt() {
-    // tim3 isr has much lower priority than systick, so it is necessary to disable the systick
-    // until sleeping task has successfully restored. otherwise it may occur that the restore
-    // gets interrupted by systick and the overlaying context switch destroys the task switch workflow
-    disable_systick();
-    save_task_context();
-    tim3::stop();
+pub unsafe extern "C" fn FaultHandler(frame: *const u32, exc_return: u32) {
+    let vector = scb::ICSR.read_field(scb::VECTACTIVE);
+    // bit 2 of exc_return is set when the psp was active, which
+    // is only the case while a task was running in thread mode
+    let raised_by_task = exc_return & (0b1 << exc_return::SPSEL) != 0;
+
+    dev::uart::print_str("\n\r---- !!!");
+    dev::uart::print_str(fault::status::name(vector));
+    dev::uart::print_str("!!! ----\n\r");
+    if raised_by_task {
+        dev::uart::print_str("  pid: ");
+        dev::uart::print_dec(task_control::current_pid());
+        dev::uart::print_str("\n\r");
+    }
+    // the frame is missing if stacking itself failed, e.g. the
+    // psp pointed into the guard region below the task stack
+    if fault::status::frame_stacked() {
+        dev::uart::print_str("  pc: ");
+        dev::uart::print_hex(unsafe { *frame.add(6) });
+        dev::uart::print_str("\n\r");
+    }
+    fault::status::print_causes();
+    fault::status::clear();
+
+    if raised_by_task {
+        dev::uart::print_str("  task terminated\n\r");
+        dev::gpio_owner::release_all(task_control::current_pid());
+        task_control::terminate_task();
+        set_pending();
+        return;
+    }
+    dev::uart::print_str("  kernel halted\n\r");
+    loop {
+        unsafe {
+            __wait_for_interrupt();
+        }
+    }
+}
 
-    // clear interrupt flag to prevent hanging in ISR
-    tim3::clear_uif();
+///
+/// Common entry for every peripheral interrupt, see `interrupts::irq`.
+///
+#[no_mangle]
+pub extern "C" fn IrqDispatcher() {
+    interrupts::irq::dispatch();
+}
 
-    // reset counter value to 0
-    tim3::flush();
+///
+/// Interrupt Service Routine when timer 3 cnt register reaches
+/// value in timer 3 auto reload register.
+///
+fn tim3_interrupt() {
+    // the restore of the sleeping task happens within pendsv. systick stays
+    // disabled until then, otherwise it may trigger a second context switch
+    // which would schedule the round robin successor instead.
+    disable_systick();
+    // stops the timer and clears the interrupt flag
+    // to prevent hanging in the isr
+    sleep_timer::acknowledge();
 
-    load_sleeping_task();
-    enable_systick();
+    task_control::wake_sleeping();
+    set_pending();
 }
 
 ///
 /// Manually create a section which points to the adress of
 /// the reset function.
 ///
+#[cfg(target_os = "none")]
 #[link_section = ".vector_table.reset"]
 #[no_mangle]
 pub static RESET: [VectorDivergentFn; 1] = [VectorDivergentFn { handler: Reset }];
 
+#[cfg(target_os = "none")]
 #[link_section = ".vector_table.exceptions"]
 #[no_mangle]
-pub static EXCEPTIONS: [Vector; 52] = [
+pub static EXCEPTIONS: [Vector; 14] = [
     Vector { handler: NMI },
     Vector { handler: HardFault },
     Vector { handler: MemManage },
@@ -295,117 +616,17 @@ pub static EXCEPTIONS: [Vector; 52] = [
     Vector { reserved: 0 },
     Vector { handler: PendSV },
     Vector { handler: SysTick },
-    Vector { reserved: 0 }, //wwdg pos 0
-    // why reading docs when you can try'n error?? ;p
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
-    Vector {
-        handler: Tim3Interrupt,
-    },
 ];
+
+#[cfg(target_os = "none")]
+const DISPATCH: Vector = Vector { handler: IrqDispatcher };
+
+///
+/// Peripheral interrupts, starting with wwdg at position 0.
+/// All of them end up in the dispatcher, handlers get
+/// attached during runtime with `interrupts::irq::register_irq`.
+///
+#[cfg(target_os = "none")]
+#[link_section = ".vector_table.interrupts"]
+#[no_mangle]
+pub static INTERRUPTS: [Vector; irqn::COUNT] = [DISPATCH; irqn::COUNT];
@@ -1,12 +1,509 @@
-pub mod memory_handler {
-    use core::ptr;
+///
+/// Pools of equal sized blocks for deterministic allocations. The
+/// storage of a pool gets taken from the kernel heap once on creation,
+/// afterwards alloc and free only pop and push an intrusive free list,
+/// so both run in constant time and never fragment. They run within a
+/// critical section and may be called from interrupt handlers, as long
+/// as the handler does not block. Freeing a block while tasks wait on
+/// the pool hands the block directly to the first of them. The pool
+/// keeps track of the blocks in use, so a block can be freed only once.
+///
+pub mod pool {
+    use super::heap;
+    use crate::sched::{critical, event, task_control};
+    use core::ptr::addr_of;
 
-    pub fn write(dest: u32, content: u32) {
+    const MAX_POOLS: usize = 8;
+    // blocks per pool, one bit of `used` each
+    const MAX_BLOCKS: u32 = 256;
+    const USED_WORDS: usize = MAX_BLOCKS as usize / 32;
+    // marks the end of the free list
+    const NIL: u32 = 0;
+    // timeout values of `alloc_or_block`
+    pub const NO_WAIT: u32 = 0;
+    pub const FOREVER: u32 = u32::MAX;
+
+    struct MemoryPool {
+        storage: usize,
+        block_size: u32,
+        blocks: u32,
+        // index + 1 of the first free block, each free block holds
+        // the link to the next one in its first word
+        free_list: u32,
+        free_count: u32,
+        // event flag the waiting tasks block on
+        event: u32,
+        // blocks handed out, kept outside of the blocks themselves
+        // as the tasks may overwrite those
+        used: [u32; USED_WORDS],
+    }
+
+    const EMPTY: Option<MemoryPool> = None;
+    static mut POOLS: [Option<MemoryPool>; MAX_POOLS] = [EMPTY; MAX_POOLS];
+
+    fn get_pool<'a>(id: u32) -> Option<&'a mut MemoryPool> {
+        if id as usize >= MAX_POOLS {
+            return None;
+        }
+        unsafe { POOLS[id as usize].as_mut() }
+    }
+
+    // the adress of a block as seen by the tasks
+    fn adress(pool: &MemoryPool, index: u32) -> u32 {
+        (pool.storage as u32).wrapping_add(index * pool.block_size)
+    }
+
+    fn link_of(pool: &MemoryPool, index: u32) -> *mut u32 {
+        (pool.storage + (index * pool.block_size) as usize) as *mut u32
+    }
+
+    // the index of `block` if it is the start of a block of the pool
+    fn index_of(pool: &MemoryPool, block: u32) -> Option<u32> {
+        let offset = block.wrapping_sub(pool.storage as u32);
+        match offset.is_multiple_of(pool.block_size) && offset / pool.block_size < pool.blocks {
+            true => Some(offset / pool.block_size),
+            false => None,
+        }
+    }
+
+    fn is_used(pool: &MemoryPool, index: u32) -> bool {
+        pool.used[index as usize / 32] & (0b1 << (index % 32)) != 0
+    }
+
+    fn set_used(pool: &mut MemoryPool, index: u32, used: bool) {
+        let word = &mut pool.used[index as usize / 32];
+        match used {
+            true => *word |= 0b1 << (index % 32),
+            false => *word &= !(0b1 << (index % 32)),
+        }
+    }
+
+    fn pop(pool: &mut MemoryPool) -> Option<u32> {
+        let index = match pool.free_list {
+            NIL => return None,
+            link => link - 1,
+        };
+        let next = unsafe { *link_of(pool, index) };
+        pool.free_count -= 1;
+        // a link overwritten by a task drops the rest of the list
+        // instead of handing out a foreign or used block
+        pool.free_list = match next {
+            n if n != NIL && (n > pool.blocks || is_used(pool, n - 1)) => {
+                pool.free_count = 0;
+                NIL
+            }
+            n => n,
+        };
+        set_used(pool, index, true);
+        Some(adress(pool, index))
+    }
+
+    fn push(pool: &mut MemoryPool, index: u32) {
         unsafe {
-            ptr::write(dest as *mut u32, content);
+            *link_of(pool, index) = pool.free_list;
+        }
+        set_used(pool, index, false);
+        pool.free_list = index + 1;
+        pool.free_count += 1;
+    }
+
+    ///
+    /// Creates a pool of `blocks` blocks with at least `block_size`
+    /// bytes each. The size gets rounded up to a multiple of 8 so every
+    /// block stays aligned. Returns the id of the pool or None if the
+    /// heap, the pool table or the event flags are exhausted or the
+    /// pool would have more than 256 blocks.
+    ///
+    pub fn create(block_size: u32, blocks: u32) -> Option<u32> {
+        if block_size == 0 || blocks == 0 || blocks > MAX_BLOCKS {
+            return None;
+        }
+        let block_size = (block_size + 7) & !7;
+        let size = block_size.checked_mul(blocks)?;
+        critical::section(|| {
+            let id = unsafe { (*addr_of!(POOLS)).iter().position(|p| p.is_none()) }?;
+            let event = event::allocate()?;
+            let storage = heap::kernel_alloc(size as usize, 8) as usize;
+            if storage == 0 {
+                return None;
+            }
+            let mut pool = MemoryPool {
+                storage,
+                block_size,
+                blocks,
+                free_list: NIL,
+                free_count: 0,
+                event,
+                used: [0; USED_WORDS],
+            };
+            // link the blocks in ascending order
+            for i in (0..blocks).rev() {
+                push(&mut pool, i);
+            }
+            unsafe {
+                POOLS[id] = Some(pool);
+            }
+            Some(id as u32)
+        })
+    }
+
+    ///
+    /// Takes a block of the pool without blocking.
+    /// Returns None if the pool is empty or does not exist.
+    ///
+    pub fn try_alloc(id: u32) -> Option<u32> {
+        critical::section(|| match get_pool(id) {
+            Some(pool) => pop(pool),
+            None => None,
+        })
+    }
+
+    ///
+    /// Takes a block of the pool for the current task. If the pool is
+    /// empty the task gets blocked for at most `timeout` ticks, FOREVER
+    /// waits until a block is freed and NO_WAIT returns immediately.
+    /// The adress of the block, or 0 on failure, gets written to `result`,
+    /// by the waker if the task had to block. Returns true in that case
+    /// and the caller has to trigger a context switch.
+    /// # Safety
+    /// `result` has to stay valid until the task got a block or gave up.
+    ///
+    pub unsafe fn alloc_or_block(id: u32, timeout: u32, result: *mut u32) -> bool {
+        critical::section(|| {
+            let pool = match get_pool(id) {
+                Some(p) => p,
+                None => {
+                    unsafe { *result = 0 };
+                    return false;
+                }
+            };
+            match pop(pool) {
+                Some(block) => {
+                    unsafe { *result = block };
+                    false
+                }
+                None if timeout == NO_WAIT => {
+                    unsafe { *result = 0 };
+                    false
+                }
+                None => {
+                    let ticks = match timeout {
+                        FOREVER => 0,
+                        t => t,
+                    };
+                    task_control::block_on(pool.event, ticks, result);
+                    true
+                }
+            }
+        })
+    }
+
+    ///
+    /// Returns a block to its pool. Returns false if the block
+    /// does not belong to the pool or is free already.
+    ///
+    pub fn free(id: u32, block: u32) -> bool {
+        critical::section(|| {
+            let pool = match get_pool(id) {
+                Some(p) => p,
+                None => return false,
+            };
+            let index = match index_of(pool, block) {
+                Some(i) if is_used(pool, i) => i,
+                _ => return false,
+            };
+            // a waiting task takes the block over, it stays in use
+            if !task_control::wake_one(pool.event, block) {
+                push(pool, index);
+            }
+            true
+        })
+    }
+
+    ///
+    /// Number of free blocks of the pool.
+    ///
+    pub fn available(id: u32) -> Option<u32> {
+        critical::section(|| get_pool(id).map(|p| p.free_count))
+    }
+}
+
+///
+/// First-fit allocator over the section .kernel_heap, registered as
+/// global allocator so `alloc::boxed::Box` and `alloc::vec::Vec` work
+/// in kernel and user code. Tasks run unprivileged and can not mask
+/// interrupts, so their requests get forwarded to the kernel via syscall.
+/// The mpu opens the heap for all tasks, which means blocks are not
+/// protected against other tasks.
+///
+/// Frees of unknown or already freed blocks get refused. With the
+/// feature "heap-debug" every operation validates the block headers
+/// and panics on a corrupted heap.
+///
+pub mod heap {
+    use crate::generic::config::HEAP_SIZE;
+    use crate::sched::critical;
+    use crate::sys::call_api;
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::mem::{size_of, MaybeUninit};
+    use core::ptr::addr_of_mut;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    extern "C" {
+        fn __in_unprivileged_thread() -> u32;
+    }
+
+    // the header of every block. its size keeps the payload 8 byte aligned.
+    #[repr(C)]
+    struct Header {
+        // size of the whole block including this header
+        size: u32,
+        tag: u32,
+    }
+
+    const HEADER_SIZE: usize = size_of::<Header>();
+    // header plus the smallest payload
+    const MIN_BLOCK: usize = HEADER_SIZE + 8;
+    const TAG_FREE: u32 = 0xF2EE_B10C;
+    const TAG_USED: u32 = 0x05ED_B10C;
+
+    #[repr(C, align(8))]
+    struct HeapArea([u8; HEAP_SIZE]);
+
+    #[link_section = ".kernel_heap"]
+    static mut HEAP_AREA: MaybeUninit<HeapArea> = MaybeUninit::uninit();
+    static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+    pub struct HeapStats {
+        pub used: u32,
+        pub free: u32,
+        pub largest_free: u32,
+        // share of the free memory which is not part of the
+        // largest free block, in percent
+        pub fragmentation: u32,
+    }
+
+    // older toolchains demand unsafe for the adress of a static mut
+    #[allow(unused_unsafe)]
+    fn start() -> usize {
+        unsafe { addr_of_mut!(HEAP_AREA) as usize }
+    }
+
+    fn end() -> usize {
+        start() + HEAP_SIZE
+    }
+
+    fn header<'a>(block: usize) -> &'a mut Header {
+        unsafe { &mut *(block as *mut Header) }
+    }
+
+    fn align_up(value: usize, align: usize) -> usize {
+        (value + align - 1) & !(align - 1)
+    }
+
+    // the section is not initialized on startup, so the first
+    // request turns the whole area into one free block
+    fn init() {
+        if !INITIALIZED.load(Ordering::Relaxed) {
+            let first = header(start());
+            first.size = HEAP_SIZE as u32;
+            first.tag = TAG_FREE;
+            INITIALIZED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn verify(block: usize) {
+        let h = header(block);
+        let size = h.size as usize;
+        if (h.tag != TAG_FREE && h.tag != TAG_USED) || size < MIN_BLOCK || !size.is_multiple_of(8) || block + size > end() {
+            panic!("heap corrupted at {:#x}", block);
+        }
+    }
+
+    fn check_heap() {
+        let mut block = start();
+        while block < end() {
+            verify(block);
+            block += header(block).size as usize;
+        }
+    }
+
+    // merges every run of adjacent free blocks
+    fn coalesce() {
+        let mut block = start();
+        while block < end() {
+            let h = header(block);
+            let next = block + h.size as usize;
+            if h.tag == TAG_FREE && next < end() && header(next).tag == TAG_FREE {
+                h.size += header(next).size;
+                continue;
+            }
+            block = next;
+        }
+    }
+
+    // splits `block` after `size` bytes if the remainder forms a usable block
+    fn split(block: usize, size: usize) {
+        let h = header(block);
+        let rest = h.size as usize - size;
+        if rest >= MIN_BLOCK {
+            let tail = header(block + size);
+            tail.size = rest as u32;
+            tail.tag = TAG_FREE;
+            h.size = size as u32;
         }
     }
-    pub fn read(dest: u32) -> u32 {
-        unsafe { ptr::read_volatile(dest as *const u32) }
+
+    fn allocate(size: usize, align: usize) -> *mut u8 {
+        init();
+        if cfg!(feature = "heap-debug") {
+            check_heap();
+        }
+        let size = align_up(size.max(1), 8);
+        let align = align.max(8);
+
+        let mut block = start();
+        while block < end() {
+            let h = header(block);
+            let block_size = h.size as usize;
+            if h.tag == TAG_FREE {
+                // an aligned payload may require a leading free block,
+                // which needs enough space for its own header
+                let mut payload = align_up(block + HEADER_SIZE, align);
+                while payload - HEADER_SIZE != block && payload - HEADER_SIZE - block < MIN_BLOCK {
+                    payload += align;
+                }
+                let used = payload - block + size;
+                if used <= block_size {
+                    let mut target = block;
+                    if payload - HEADER_SIZE != block {
+                        split(block, payload - HEADER_SIZE - block);
+                        target = payload - HEADER_SIZE;
+                    }
+                    split(target, HEADER_SIZE + size);
+                    header(target).tag = TAG_USED;
+                    return payload as *mut u8;
+                }
+            }
+            block += block_size;
+        }
+        core::ptr::null_mut()
     }
+
+    // the block of `ptr` if it is the payload of a used block. walks
+    // the heap, so a forged header within a payload gets refused too.
+    fn used_block(ptr: usize) -> Option<usize> {
+        if ptr < start() + HEADER_SIZE || ptr >= end() || !ptr.is_multiple_of(8) {
+            return None;
+        }
+        let wanted = ptr - HEADER_SIZE;
+        let mut block = start();
+        while block < wanted {
+            // tasks may overwrite headers, a broken one ends the walk
+            let size = header(block).size as usize;
+            if size < MIN_BLOCK {
+                return None;
+            }
+            block += size;
+        }
+        match block == wanted && header(block).tag == TAG_USED {
+            true => Some(block),
+            false => None,
+        }
+    }
+
+    fn deallocate(ptr: *mut u8) -> bool {
+        init();
+        if cfg!(feature = "heap-debug") {
+            check_heap();
+        }
+        let block = match used_block(ptr as usize) {
+            Some(b) => b,
+            None => return false,
+        };
+        header(block).tag = TAG_FREE;
+        coalesce();
+        true
+    }
+
+    ///
+    /// Allocates `size` bytes aligned to `align` within the kernel.
+    /// Returns a null pointer if no free block is large enough.
+    ///
+    pub fn kernel_alloc(size: usize, align: usize) -> *mut u8 {
+        critical::section(|| allocate(size, align))
+    }
+
+    ///
+    /// Returns a block to the heap. False, without touching the heap,
+    /// if `ptr` is no block obtained by `kernel_alloc` or freed already.
+    ///
+    pub fn kernel_free(ptr: *mut u8) -> bool {
+        match ptr.is_null() {
+            true => false,
+            false => critical::section(|| deallocate(ptr)),
+        }
+    }
+
+    ///
+    /// Walks through the heap and sums up the blocks.
+    ///
+    pub fn stats() -> HeapStats {
+        critical::section(|| {
+            init();
+            let mut stats = HeapStats {
+                used: 0,
+                free: 0,
+                largest_free: 0,
+                fragmentation: 0,
+            };
+            let mut block = start();
+            while block < end() {
+                let h = header(block);
+                if h.tag == TAG_FREE {
+                    stats.free += h.size;
+                    stats.largest_free = stats.largest_free.max(h.size);
+                } else {
+                    stats.used += h.size;
+                }
+                block += h.size as usize;
+            }
+            // without free space there is nothing to fragment
+            if let Some(largest) = (stats.largest_free * 100).checked_div(stats.free) {
+                stats.fragmentation = 100 - largest;
+            }
+            stats
+        })
+    }
+
+    ///
+    /// Base adress and size of the heap, for the mpu.
+    ///
+    pub fn region() -> (u32, u32) {
+        (start() as u32, HEAP_SIZE as u32)
+    }
+
+    pub struct KernelHeap;
+
+    unsafe impl GlobalAlloc for KernelHeap {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            if __in_unprivileged_thread() != 0 {
+                return call_api::heap_alloc(layout.size() as u32, layout.align() as u32) as *mut u8;
+            }
+            kernel_alloc(layout.size(), layout.align())
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+            if __in_unprivileged_thread() != 0 {
+                call_api::heap_free(ptr as u32);
+                return;
+            }
+            kernel_free(ptr);
+        }
+    }
+
+    // host builds keep the allocator of std
+    #[cfg(target_os = "none")]
+    #[global_allocator]
+    static ALLOCATOR: KernelHeap = KernelHeap;
 }
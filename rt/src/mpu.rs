@@ -0,0 +1,163 @@
+//!
+//! Memory protection between the tasks. The regions get
+//! reprogrammed on each context switch, so the running task
+//! may only access its own stack besides the shared flash.
+//!
+use super::cpu;
+use super::platform;
+pub mod mpu_handler {
+    use super::cpu::c_registers::mpu;
+    use super::platform::adresses;
+    use crate::generic::config;
+    use crate::mem::heap;
+    use crate::sched::process;
+
+    // lowest bytes of every task stack, no one may access them. a task
+    // running into it raises a memmanage fault instead of silently
+    // overwriting the neighbour stack. 32 bytes is the minimal region size.
+    pub const GUARD_SIZE: u32 = 32;
+
+    // region sizes have to be a power of two
+    const FLASH_SIZE: u32 = config::FLASH_SIZE.next_power_of_two() as u32;
+    const SRAM_SIZE: u32 = config::SRAM_SIZE.next_power_of_two() as u32;
+
+    // higher region numbers take precedence where regions overlap
+    const REGION_FLASH: u32 = 0;
+    const REGION_SRAM: u32 = 1;
+    const REGION_HEAP: u32 = 2;
+    const REGION_TASK_STACK: u32 = 3;
+    const REGION_GUARD: u32 = 4;
+
+    // normal memory, write-through, not shareable
+    const FLASH_ATTRIBUTES: u32 = mpu::C.mask();
+    // normal memory, write-back, shareable, never executed
+    const SRAM_ATTRIBUTES: u32 = mpu::XN.mask() | mpu::S.mask() | mpu::C.mask() | mpu::B.mask();
+
+    fn is_present() -> bool {
+        mpu::TYPE.read_field(mpu::DREGION) != 0
+    }
+
+    ///
+    /// Base adress register of a region. The base has to be aligned
+    /// to the size of the region, the lowest 5 bits get dropped anyway.
+    ///
+    pub fn rbar(base: u32) -> u32 {
+        base & mpu::ADDR.mask()
+    }
+
+    ///
+    /// Attribute and size register of an enabled region.
+    /// # Arguments
+    /// * `size` - An u32 value, a power of two of at least 32 bytes
+    /// * `attributes` - An u32 value, the memory type bits and xn
+    /// * `access` - An u32 value, one of the mpu::AP_* permissions
+    ///
+    pub fn rasr(size: u32, attributes: u32, access: u32) -> u32 {
+        // a region of 2^(n+1) bytes is encoded as n
+        let size_field = size.trailing_zeros() - 1;
+        attributes | mpu::AP.value(access) | mpu::SIZE.value(size_field) | mpu::REGION_ENABLE.mask()
+    }
+
+    fn set_region(number: u32, base: u32, size: u32, attributes: u32, access: u32) {
+        mpu::RNR.write(number);
+        mpu::RBAR.write(rbar(base));
+        mpu::RASR.write(rasr(size, attributes, access));
+    }
+
+    ///
+    /// Sets up the regions shared by all tasks and enables the mpu.
+    /// Code and constants are readable for everyone, the sram belongs
+    /// to the kernel except for the heap. The privileged default map stays active for
+    /// everything else, like the peripherals.
+    ///
+    pub fn init() {
+        if !is_present() {
+            return;
+        }
+        set_region(
+            REGION_FLASH,
+            adresses::FLASH,
+            FLASH_SIZE,
+            FLASH_ATTRIBUTES,
+            mpu::AP_READ_ONLY,
+        );
+        set_region(
+            REGION_SRAM,
+            adresses::SRAM,
+            SRAM_SIZE,
+            SRAM_ATTRIBUTES,
+            mpu::AP_PRIV_RW,
+        );
+        let (heap_base, heap_size) = heap::region();
+        set_region(
+            REGION_HEAP,
+            heap_base,
+            heap_size,
+            SRAM_ATTRIBUTES,
+            mpu::AP_FULL_ACCESS,
+        );
+        mpu::CTRL.write(mpu::PRIVDEFENA.mask() | mpu::ENABLE.mask());
+    }
+
+    ///
+    /// Number of bytes from `adress` up to the end of the region
+    /// which contains it, 0 if the task with the given pid may not
+    /// access `adress`. Matches the regions the mpu opens for the task:
+    /// its own stack above the guard region and the heap, the flash
+    /// only if nothing gets written.
+    ///
+    pub fn accessible(pid: u32, adress: u32, write: bool) -> u32 {
+        let mut regions = [(0, 0); 3];
+        if let Some((base, size)) = process::stack_region(pid) {
+            regions[0] = (base + GUARD_SIZE, size - GUARD_SIZE);
+        }
+        regions[1] = heap::region();
+        if !write {
+            regions[2] = (adresses::FLASH, config::FLASH_SIZE as u32);
+        }
+        for (base, size) in regions {
+            let offset = adress.wrapping_sub(base);
+            if offset < size {
+                return size - offset;
+            }
+        }
+        0
+    }
+
+    ///
+    /// True if the task with the given pid may access all `len`
+    /// bytes at `adress`, see `accessible`.
+    ///
+    pub fn task_may_access(pid: u32, adress: u32, len: u32, write: bool) -> bool {
+        let available = accessible(pid, adress, write);
+        available != 0 && len <= available
+    }
+
+    ///
+    /// Opens the stack of the task with the given pid and
+    /// places the guard region at its lower end.
+    ///
+    pub fn switch_to(pid: u32) {
+        if !is_present() {
+            return;
+        }
+        let (base, size) = match process::stack_region(pid) {
+            Some(region) => region,
+            None => return,
+        };
+        set_region(
+            REGION_TASK_STACK,
+            base,
+            size,
+            SRAM_ATTRIBUTES,
+            mpu::AP_FULL_ACCESS,
+        );
+        set_region(
+            REGION_GUARD,
+            base,
+            GUARD_SIZE,
+            SRAM_ATTRIBUTES,
+            mpu::AP_NO_ACCESS,
+        );
+    }
+}
@@ -3,11 +3,51 @@
 //! methods to control task switching.
 //!
 use super::sys;
+
+///
+/// Sections which must not get interrupted, e.g. because an interrupt
+/// handler accesses the same data. They nest, each restores the
+/// interrupt mask it found.
+///
+pub mod critical {
+    extern "C" {
+        fn __enter_critical() -> u32;
+        fn __exit_critical(primask: u32);
+    }
+
+    pub(crate) fn section<R>(f: impl FnOnce() -> R) -> R {
+        unsafe {
+            let primask = __enter_critical();
+            let result = f();
+            __exit_critical(primask);
+            result
+        }
+    }
+}
+
 pub mod process {
     use super::sys::call_api;
+    use super::task_control;
+    use crate::generic::config::{MAX_TASKS, TASK_STACK_SIZE};
+    use crate::mpu::mpu_handler::GUARD_SIZE;
+    use core::mem::{size_of, MaybeUninit};
+    use core::ptr::{self, addr_of, addr_of_mut};
+
+    // every byte of an unused stack holds this value, so the
+    // deepest point a task ever reached can be measured afterwards
+    const STACK_FILL: u8 = 0xA5;
+    const STACK_PATTERN: u32 = 0xA5A5_A5A5;
+    // lowest word above the mpu guard region of each stack,
+    // gets overwritten on an overflow
+    const STACK_CANARY: u32 = 0xDEAD_BEEF;
+    const CANARY: usize = GUARD_SIZE as usize / 4;
+
+    ///
+    /// Initial stack of a task. It starts with a basic exception frame,
+    /// the fpu registers only get stacked once the task uses the fpu.
+    ///
     #[repr(C)]
     pub struct ProcessFrame {
-        free_space: [u32; 256],
         pub r4: u32,
         r5: u32,
         r6: u32,
@@ -29,7 +69,6 @@ pub mod process {
     impl ProcessFrame {
         pub fn new(target: u32) -> ProcessFrame {
             ProcessFrame {
-                free_space: unsafe { core::mem::zeroed() },
                 r4: 0,
                 r5: 0,
                 r6: 0,
@@ -49,10 +88,115 @@ pub mod process {
             }
         }
     }
+
+    // the stack pointer has to be 8 byte aligned on exception entry.
+    // the linker script aligns the section to the stack size, which
+    // keeps every stack usable as mpu region.
+    #[repr(C, align(8))]
+    struct Stack([u32; TASK_STACK_SIZE / 4]);
+
+    const STACK_WORDS: usize = TASK_STACK_SIZE / 4;
+
+    ///
+    /// Stacks of all tasks, located in the section .task_stacks
+    /// which is reserved by the linker script. Task n uses stack n.
+    ///
+    #[link_section = ".task_stacks"]
+    static mut TASK_STACKS: MaybeUninit<[Stack; MAX_TASKS]> = MaybeUninit::uninit();
+
+    fn get_stack(pid: u32) -> *mut Stack {
+        unsafe { (addr_of_mut!(TASK_STACKS) as *mut Stack).add(pid as usize) }
+    }
+
+    ///
+    /// Creates a new task which starts at `target`. The initial frame
+    /// gets placed at the top of the next free task stack, the rest
+    /// of the stack gets filled with a known pattern and the canary.
+    /// The last stack is kept for the idle task of the scheduler.
+    /// Returns the pid of the task or None if all stacks are in use.
+    ///
+    pub fn create(target: u32) -> Option<u32> {
+        if task_control::task_count() as usize >= MAX_TASKS - 1 {
+            return None;
+        }
+        spawn(target)
+    }
+
+    ///
+    /// Creates the idle task on the stack kept for it, see `create`.
+    ///
+    pub(crate) fn create_idle(target: u32) -> Option<u32> {
+        spawn(target)
+    }
+
+    fn spawn(target: u32) -> Option<u32> {
+        let slot = task_control::task_count();
+        if slot as usize >= MAX_TASKS {
+            return None;
+        }
+        unsafe {
+            let stack = get_stack(slot);
+            ptr::write_bytes(stack as *mut u8, STACK_FILL, size_of::<Stack>());
+            (*stack).0[CANARY] = STACK_CANARY;
+            let frame = (stack as *mut u8).add(size_of::<Stack>() - size_of::<ProcessFrame>()) as *mut ProcessFrame;
+            frame.write(ProcessFrame::new(target));
+            task_control::insert(addr_of!((*frame).r4) as u32)
+        }
+    }
+
+    ///
+    /// Returns false if the task has written beyond the end of its stack.
+    ///
+    pub fn canary_intact(pid: u32) -> bool {
+        match unsafe { stack_of(pid) } {
+            Some(stack) => stack[CANARY] == STACK_CANARY,
+            None => true,
+        }
+    }
+
+    ///
+    /// Words of the task's stack, the lowest adress first.
+    /// None if there is no task with this pid.
+    /// # Safety
+    /// The task must not run while the slice is in use, e.g.
+    /// because the kernel handles one of its traps.
+    ///
+    pub unsafe fn stack_of<'a>(pid: u32) -> Option<&'a mut [u32]> {
+        if pid >= task_control::task_count() {
+            return None;
+        }
+        Some(&mut (*get_stack(pid)).0)
+    }
+
+    ///
+    /// Base adress and size of the task's stack.
+    ///
+    pub fn stack_region(pid: u32) -> Option<(u32, u32)> {
+        if pid >= task_control::task_count() {
+            return None;
+        }
+        Some((get_stack(pid) as u32, TASK_STACK_SIZE as u32))
+    }
+
+    ///
+    /// High-water mark of the task's stack, the maximum amount of
+    /// bytes it ever used. None if there is no task with this pid.
+    ///
+    pub fn stack_usage(pid: u32) -> Option<u32> {
+        let stack = unsafe { stack_of(pid)? };
+        // the first word above the canary which differs
+        // from the pattern marks the deepest point
+        let untouched = stack[CANARY + 1..].iter().take_while(|w| **w == STACK_PATTERN).count() + CANARY + 1;
+        Some(((STACK_WORDS - untouched) * 4) as u32)
+    }
 }
 
 pub mod task_control {
-    use core::sync::atomic::{AtomicU32, Ordering};
+    use crate::generic::config::MAX_TASKS;
+    use crate::generic::cpu::c_bitfields::exc_return;
+    use core::mem::MaybeUninit;
+    use core::ptr::addr_of_mut;
+    use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
     /// Represents the possible states of a task.
     pub enum TaskStates {
@@ -64,6 +208,9 @@ pub mod task_control {
 
     ///
     /// Task Control Block, 32 Bit aligned.
+    /// The fpu registers s16 - s31 only get pushed onto the task stack
+    /// if the task used the fpu, which is recorded by bit 4 of the
+    /// exc_return value the task got interrupted with.
     ///
     #[repr(C)]
     #[repr(align(4))]
@@ -74,12 +221,36 @@ pub mod task_control {
         state: TaskStates,
         // Task number
         pid: u32,
+        // Event flags the task is blocked on, 0 if none
+        wait_mask: u32,
+        // Tick at which a blocking call gives up, 0 waits forever
+        deadline: u32,
+        // Where the outcome of a blocking call gets written to, null if none
+        wait_result: *mut u32,
+        // Exception return value to resume the task with
+        exc_return: u32,
     }
 
     pub fn update_sp(new_sp: u32) {
+        if let Some(t) = get_current_tcb() {
+            t.sp = new_sp;
+        }
+    }
+
+    pub fn update_exc_return(exc_return: u32) {
+        if let Some(t) = get_current_tcb() {
+            t.exc_return = exc_return;
+        }
+    }
+
+    ///
+    /// Exception return value of the current task, tells whether
+    /// its stack holds a basic or an extended (fpu) frame.
+    ///
+    pub fn current_exc_return() -> u32 {
         match get_current_tcb() {
-            Some(t) => t.sp = new_sp,
-            None => {}
+            Some(t) => t.exc_return,
+            None => exc_return::THREAD_PSP,
         }
     }
 
@@ -88,22 +259,30 @@ pub mod task_control {
     /// by enum TaskStates
     ///
     pub fn set_task_state(new_state: TaskStates) {
-        match get_current_tcb() {
-            Some(t) => t.state = new_state,
-            None => {}
+        if let Some(t) = get_current_tcb() {
+            t.state = new_state;
         }
     }
 
     ///
-    /// Tasklist is fix located at 0x2000_0200.
-    /// TODO: Reserve this area in linker script somehow.
+    /// Tasklist, located in the section .kernel_tcbs which is reserved
+    /// by the linker script. Only the first HEAP_SIZE entries are valid.
     ///
-    const TCB_START: u32 = 0x2000_0200;
-    const TCB_SIZE: u32 = core::mem::size_of::<TCB>() as u32;
+    #[link_section = ".kernel_tcbs"]
+    static mut TCB_TABLE: MaybeUninit<[Option<TCB>; MAX_TASKS]> = MaybeUninit::uninit();
 
     static HEAP_SIZE: AtomicU32 = AtomicU32::new(0);
     pub static CURRENTLY_SLEEPING: AtomicU32 = AtomicU32::new(0);
     pub static CURRENT_TASK: AtomicU32 = AtomicU32::new(0);
+    static SLEEPER_WOKEN: AtomicBool = AtomicBool::new(false);
+    // runs whenever every other task is blocked
+    static IDLE_TASK: AtomicU32 = AtomicU32::new(u32::MAX);
+    // systick periods since the scheduler started
+    static TICKS: AtomicU32 = AtomicU32::new(0);
+
+    // outcome of a blocking call, written to its wait_result adress
+    pub const WAKE_TIMEOUT: u32 = 0;
+    pub const WAKE_SIGNALLED: u32 = 1;
 
     ///
     /// Loads table index of task which has currently the state "SLEEPING",
@@ -111,9 +290,8 @@ pub mod task_control {
     /// adress from it.
     ///
     pub fn get_sleeping() -> u32 {
-        let currently_sleeping = CURRENTLY_SLEEPING.load(Ordering::Relaxed) as u32;
-        let target_tcb_adress = (currently_sleeping * TCB_SIZE) + TCB_START;
-        let tcb = unsafe { &mut *(target_tcb_adress as *mut Option<TCB>) };
+        let currently_sleeping = CURRENTLY_SLEEPING.load(Ordering::Relaxed);
+        let tcb = get_tcb(currently_sleeping);
         CURRENT_TASK.store(currently_sleeping, Ordering::Relaxed);
 
         match tcb {
@@ -125,46 +303,81 @@ pub mod task_control {
         }
     }
 
+    fn is_runnable(pid: u32) -> bool {
+        match get_tcb(pid) {
+            Some(t) => !matches!(t.state, TaskStates::BLOCKED | TaskStates::TERMINATED),
+            None => false,
+        }
+    }
+
+    ///
+    /// Selects the successor of the current task in round robin order,
+    /// skipping blocked and terminated tasks. The idle task only gets
+    /// selected if no other task is able to run.
+    ///
     pub fn next_process() -> u32 {
-        let current = CURRENT_TASK.fetch_add(1, Ordering::Relaxed) as u32;
-        let next = (current + 1) % HEAP_SIZE.load(Ordering::Relaxed);
-        let target_tcb_adress = (next * TCB_SIZE) + TCB_START;
-        let tcb = unsafe { &mut *(target_tcb_adress as *mut Option<TCB>) };
+        let current = CURRENT_TASK.load(Ordering::Relaxed);
+        let count = HEAP_SIZE.load(Ordering::Relaxed);
+        let idle = IDLE_TASK.load(Ordering::Relaxed);
 
-        CURRENT_TASK.store(next, Ordering::Relaxed);
-        let sp_of_next_process: u32;
-        match tcb {
-            Some(t) => match t.state {
-                TaskStates::READY => {
-                    sp_of_next_process = t.sp;
-                }
-                TaskStates::BLOCKED => {
-                    sp_of_next_process = next_process();
-                }
-                TaskStates::TERMINATED => {
-                    sp_of_next_process = next_process();
-                }
-                _ => {
-                    sp_of_next_process = t.sp;
-                }
-            },
-            None => {
-                sp_of_next_process = 0x00;
+        let mut next = idle;
+        for offset in 1..=count {
+            let candidate = (current + offset) % count;
+            if candidate != idle && is_runnable(candidate) {
+                next = candidate;
+                break;
             }
         }
-        sp_of_next_process
+        if next >= count {
+            return 0x00;
+        }
+
+        CURRENT_TASK.store(next, Ordering::Relaxed);
+        match get_tcb(next) {
+            Some(t) => t.sp,
+            None => 0x00,
+        }
+    }
+
+    ///
+    /// Marks the task with the given pid as idle task.
+    ///
+    pub fn set_idle(pid: u32) {
+        IDLE_TASK.store(pid, Ordering::Relaxed);
+    }
+
+    fn get_tcb<'a>(pid: u32) -> &'a mut Option<TCB> {
+        unsafe { &mut *(addr_of_mut!(TCB_TABLE) as *mut Option<TCB>).add(pid as usize) }
     }
 
     fn get_current_tcb<'a>() -> &'a mut Option<TCB> {
-        let current = CURRENT_TASK.load(Ordering::Relaxed) as u32;
-        let target_tcb_adress = (current * TCB_SIZE) + TCB_START;
-        unsafe { &mut *(target_tcb_adress as *mut Option<TCB>) }
+        get_tcb(CURRENT_TASK.load(Ordering::Relaxed))
     }
 
-    pub fn terminate_task() {
+    ///
+    /// Number of tasks which got inserted so far.
+    ///
+    pub fn task_count() -> u32 {
+        HEAP_SIZE.load(Ordering::Relaxed)
+    }
+
+    ///
+    /// Returns the pid of the task which is currently running.
+    ///
+    pub fn current_pid() -> u32 {
+        CURRENT_TASK.load(Ordering::Relaxed)
+    }
+
+    pub fn current_terminated() -> bool {
         match get_current_tcb() {
-            Some(t) => t.state = TaskStates::TERMINATED,
-            None => {}
+            Some(t) => matches!(t.state, TaskStates::TERMINATED),
+            None => false,
+        }
+    }
+
+    pub fn terminate_task() {
+        if let Some(t) = get_current_tcb() {
+            t.state = TaskStates::TERMINATED;
         }
     }
 
@@ -173,19 +386,245 @@ pub mod task_control {
         CURRENTLY_SLEEPING.store(CURRENT_TASK.load(Ordering::Relaxed), Ordering::Relaxed);
     }
 
-    pub fn insert(stack_pointer: u32) -> u32 {
+    ///
+    /// Called from the timer isr when the sleep time is over.
+    /// The sleeping task gets loaded by the next context switch.
+    ///
+    pub fn wake_sleeping() {
+        SLEEPER_WOKEN.store(true, Ordering::Relaxed);
+    }
+
+    ///
+    /// Returns true once after `wake_sleeping` got called.
+    ///
+    pub fn take_woken() -> bool {
+        // armv6-m has no atomic swap. the timer isr only ever sets
+        // the flag, so it can not get lost in between.
+        match SLEEPER_WOKEN.load(Ordering::Relaxed) {
+            true => {
+                SLEEPER_WOKEN.store(false, Ordering::Relaxed);
+                true
+            }
+            false => false,
+        }
+    }
+
+    ///
+    /// Blocks the current task until one of the event flags in `mask`
+    /// gets signalled or `timeout` ticks passed, 0 waits forever.
+    /// The outcome gets written to `result`, if it is not null.
+    ///
+    pub fn block_on(mask: u32, timeout: u32, result: *mut u32) {
+        let deadline = match timeout {
+            0 => 0,
+            // 0 is reserved for waiting forever
+            t => TICKS.load(Ordering::Relaxed).wrapping_add(t).max(1),
+        };
+        if let Some(t) = get_current_tcb() {
+            t.state = TaskStates::BLOCKED;
+            t.wait_mask = mask;
+            t.deadline = deadline;
+            t.wait_result = result;
+        }
+    }
+
+    // releases a blocked task and reports `outcome` to it
+    fn release(t: &mut TCB, outcome: u32) {
+        if !t.wait_result.is_null() {
+            unsafe {
+                *t.wait_result = outcome;
+            }
+        }
+        t.wait_mask = 0;
+        t.deadline = 0;
+        t.wait_result = core::ptr::null_mut();
+        t.state = TaskStates::READY;
+    }
+
+    ///
+    /// Sets every task which waits on one of the flags in `mask`
+    /// back to ready. Returns true if at least one task was woken.
+    ///
+    pub fn wake_waiting(mask: u32) -> bool {
+        let mut woken = false;
+        for pid in 0..HEAP_SIZE.load(Ordering::Relaxed) {
+            let tcb = get_tcb(pid);
+            match tcb {
+                Some(t) if t.wait_mask & mask != 0 => {
+                    release(t, WAKE_SIGNALLED);
+                    woken = true;
+                }
+                _ => {}
+            }
+        }
+        woken
+    }
+
+    ///
+    /// Hands `value` over to the first task waiting on `mask`, which
+    /// becomes ready again. Returns false if no task is waiting.
+    ///
+    pub fn wake_one(mask: u32, value: u32) -> bool {
+        for pid in 0..HEAP_SIZE.load(Ordering::Relaxed) {
+            let tcb = get_tcb(pid);
+            match tcb {
+                Some(t) if t.wait_mask & mask != 0 => {
+                    release(t, value);
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    ///
+    /// Systick periods since the scheduler started.
+    ///
+    pub fn ticks() -> u32 {
+        TICKS.load(Ordering::Relaxed)
+    }
+
+    ///
+    /// Advances the system time by one systick period and
+    /// releases all tasks whose timeout expired.
+    ///
+    pub fn tick() {
+        let now = TICKS.load(Ordering::Relaxed).wrapping_add(1);
+        TICKS.store(now, Ordering::Relaxed);
+        for pid in 0..HEAP_SIZE.load(Ordering::Relaxed) {
+            let tcb = get_tcb(pid);
+            match tcb {
+                // the difference stays correct when the counter wraps
+                Some(t) if t.deadline != 0 && now.wrapping_sub(t.deadline) as i32 >= 0 => {
+                    release(t, WAKE_TIMEOUT);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    ///
+    /// Appends a task to the tasklist and returns its pid,
+    /// or None if the table is full.
+    ///
+    pub fn insert(stack_pointer: u32) -> Option<u32> {
         let pid = HEAP_SIZE.load(Ordering::Relaxed);
-        let entry_target = (pid * TCB_SIZE) + TCB_START;
-        let tcb = unsafe { &mut *(entry_target as *mut Option<TCB>) };
+        if pid as usize >= MAX_TASKS {
+            return None;
+        }
+        let tcb = get_tcb(pid);
 
         *tcb = Some(TCB {
             sp: stack_pointer,
             state: TaskStates::READY,
             pid,
+            wait_mask: 0,
+            deadline: 0,
+            wait_result: core::ptr::null_mut(),
+            exc_return: exc_return::THREAD_PSP,
         });
 
-        HEAP_SIZE.fetch_add(1, Ordering::Relaxed);
-        pid
+        HEAP_SIZE.store(pid + 1, Ordering::Relaxed);
+        Some(pid)
+    }
+
+    ///
+    /// Empties the tasklist and restarts the system time.
+    /// Only available on a host, tests start from a clean state with it.
+    ///
+    #[cfg(not(target_os = "none"))]
+    pub fn reset() {
+        HEAP_SIZE.store(0, Ordering::Relaxed);
+        CURRENTLY_SLEEPING.store(0, Ordering::Relaxed);
+        CURRENT_TASK.store(0, Ordering::Relaxed);
+        SLEEPER_WOKEN.store(false, Ordering::Relaxed);
+        IDLE_TASK.store(u32::MAX, Ordering::Relaxed);
+        TICKS.store(0, Ordering::Relaxed);
+    }
+}
+
+///
+/// Event flags as kernel objects. Interrupts or kernel services signal
+/// a flag, tasks block on them until they get signalled.
+///
+pub mod event {
+    use super::{critical, task_control};
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    // flags which got signalled while no task was waiting on them
+    static PENDING: AtomicU32 = AtomicU32::new(0);
+    // flags handed out by allocate()
+    static ALLOCATED: AtomicU32 = AtomicU32::new(0);
+
+    // interrupts signal the flags while the kernel allocates, clears
+    // or waits on them. armv6m has no atomic read-modify-write, so
+    // every update happens within a critical section.
+
+    ///
+    /// Reserves an unused event flag and returns its mask.
+    ///
+    pub fn allocate() -> Option<u32> {
+        critical::section(|| {
+            let allocated = ALLOCATED.load(Ordering::Relaxed);
+            if allocated == u32::MAX {
+                return None;
+            }
+            // lowest bit which is not set yet
+            let flag = !allocated & allocated.wrapping_add(1);
+            ALLOCATED.store(allocated | flag, Ordering::Relaxed);
+            Some(flag)
+        })
+    }
+
+    ///
+    /// Wakes up every task waiting on `mask`. If no one
+    /// is waiting the flags stay pending for the next `wait`.
+    ///
+    pub fn signal(mask: u32) {
+        critical::section(|| {
+            if !task_control::wake_waiting(mask) {
+                PENDING.store(PENDING.load(Ordering::Relaxed) | mask, Ordering::Relaxed);
+            }
+        })
+    }
+
+    ///
+    /// Drops the pending flags of `mask`, so only later signals count.
+    ///
+    pub fn clear(mask: u32) {
+        critical::section(|| {
+            PENDING.store(PENDING.load(Ordering::Relaxed) & !mask, Ordering::Relaxed);
+        })
+    }
+
+    ///
+    /// Consumes pending flags of `mask` or blocks the current task
+    /// until they get signalled, for at most `timeout` ticks (0 waits
+    /// forever). Returns true if the task got blocked, in this case the
+    /// caller has to trigger a context switch. The outcome gets written
+    /// to `result` (see task_control::WAKE_*), if it is not null.
+    /// # Safety
+    /// `result` gets written once the task got woken, so it has
+    /// to stay valid until then.
+    ///
+    pub unsafe fn wait(mask: u32, timeout: u32, result: *mut u32) -> bool {
+        critical::section(|| {
+            let pending = PENDING.load(Ordering::Relaxed);
+            if pending & mask != 0 {
+                PENDING.store(pending & !mask, Ordering::Relaxed);
+                if !result.is_null() {
+                    unsafe {
+                        *result = task_control::WAKE_SIGNALLED;
+                    }
+                }
+                return false;
+            }
+            // a signal can not slip in between, the task
+            // gets blocked before any interrupt runs
+            task_control::block_on(mask, timeout, result);
+            true
+        })
     }
 }
 
@@ -198,33 +637,86 @@ pub mod scheduler {
         /// to cpu register r0
         ///
         pub fn __write_psp(addr: u32);
-        fn __save_process_context();
-        fn __load_process_context(addr: u32);
+        fn __save_process_context(exc_return: u32);
+        fn __load_process_context(addr: u32, exc_return: u32);
         fn __get_current_psp() -> u32;
         fn __set_exc_return();
+        fn __wait_for_interrupt();
     }
-    use super::task_control::{get_sleeping, next_process, update_sp};
+    use super::process;
+    use super::task_control::{self, current_exc_return, get_sleeping, next_process, update_exc_return, update_sp};
+    use crate::dev::uart;
+    use crate::generic::cpu::c_bitfields::exc_return;
 
+    // keeps the cpu asleep while every task is blocked
+    fn idle() {
+        loop {
+            unsafe {
+                __wait_for_interrupt();
+            }
+        }
+    }
+
+    ///
+    /// Adds the idle task and loads the first task.
+    /// Gets called once all tasks are created.
+    ///
     pub fn load() {
+        match process::create_idle(idle as *const () as u32) {
+            Some(pid) => task_control::set_idle(pid),
+            // the stack of the idle task is kept free by `process::create`,
+            // without it the scheduler would load a task which does not exist
+            None => panic!("no stack left for the idle task"),
+        }
         unsafe {
-            __load_process_context(next_process());
+            let sp = next_process();
+            __load_process_context(sp, current_exc_return());
         }
     }
 
-    pub fn save_task_context() {
+    ///
+    /// Saves the context of the interrupted task.
+    /// # Arguments
+    /// * `exc_return` - An u32 value, the lr of the handler on exception entry
+    ///
+    pub fn save_task_context(exc_return: u32) {
+        // a terminated task never runs again. its stack pointer might
+        // even point into the guard region after a stack overflow.
+        if task_control::current_terminated() {
+            return;
+        }
+        // the very first context switch interrupts the kernel thread, which
+        // runs on the msp. its fpu state is of no interest for any task.
+        let exc_return = if exc_return & (0b1 << exc_return::SPSEL) != 0 {
+            exc_return
+        } else {
+            exc_return::THREAD_PSP
+        };
         unsafe {
             // loads process stack pointer value into r0,
-            // based on this adress registers r4 - r11 gets
+            // based on this adress registers s16 - s31 (only if
+            // the task used the fpu) and r4 - r11 gets
             // pushed onto the stack. after finishing this operation,
             // the new value of r0 (it points now to lower adresses
             // because registers get pushed onto it) gets assigned
             // to psp.
-            __save_process_context();
+            __save_process_context(exc_return);
 
             // the newly written process stack pointer gets written
             // into the task control block of the process table
             // for further restoring when needed
             update_sp(__get_current_psp());
+            update_exc_return(exc_return);
+        }
+
+        // the overflow may already have destroyed data of the neighbour
+        // stack, so the task must not continue in any case
+        let pid = task_control::current_pid();
+        if !process::canary_intact(pid) {
+            uart::print_str("\n\r---- !!!STACK OVERFLOW!!! ----\n\r  pid: ");
+            uart::print_dec(pid);
+            uart::print_str("\n\r  task terminated\n\r");
+            task_control::terminate_task();
         }
     }
 
@@ -234,8 +726,10 @@ pub mod scheduler {
                 // to the runnable successors task's stackpointer, which is
                 // saved in the process table. the parameter gets saved
                 // into r0, based from this value the registers r4 - r11
+                // (and s16 - s31 for an extended frame)
                 // gets popped of the stack and written into the cpu's registers.
-                __load_process_context(next_process());
+                let sp = next_process();
+                __load_process_context(sp, current_exc_return());
             
         }
     }
@@ -245,7 +739,8 @@ pub mod scheduler {
                 // the function get_sleeping returns an u32 adress
                 // to the task which is currently blocked.
                 // on the other hand it acts similiar to 'next_process()'
-                __load_process_context(get_sleeping());
+                let sp = get_sleeping();
+                __load_process_context(sp, current_exc_return());
 
         }
     }
@@ -18,7 +18,24 @@ pub mod call_api {
         YieldTask,
         TerminateTask,
         Sleep,
-        WriteStdOut
+        WriteStdOut,
+        WaitIrq,
+        StackUsage,
+        HeapAlloc,
+        HeapFree,
+        PoolCreate,
+        PoolAlloc,
+        PoolFree,
+        WaitEdge,
+        GpioClaim,
+        GpioWrite,
+        GpioRead,
+        GpioToggle,
+        PwmDuty,
+        CaptureWidth,
+        CaptureFrequency,
+        CaptureWait,
+        Exit
     }
 
     #[repr(C)]
@@ -41,7 +58,7 @@ pub mod call_api {
     pub fn enable_rt_mode() {
         let meta = TrapMeta {
             id: TrapReason::EnableRt,
-            payload: 0x0 as *const u32
+            payload: core::ptr::null()
         };
         unsafe {
             __trap(&meta);
@@ -51,27 +68,33 @@ pub mod call_api {
     pub fn start_time_measure() {
         let meta = TrapMeta {
             id: TrapReason::StartMeasurement,
-            payload: 0x0 as *const u32
+            payload: core::ptr::null()
         };
         unsafe {
             __trap(&meta);
         }
     }
 
-    pub fn stop_time_measure() {
+    ///
+    /// Returns the microseconds since `start_time_measure`,
+    /// 0 on boards without a timer to spare.
+    ///
+    pub fn stop_time_measure() -> u32 {
+        let mut elapsed: u32 = 0;
         let meta = TrapMeta {
             id: TrapReason::StopMeasurement,
-            payload: 0x0 as *const u32
+            payload: &mut elapsed as *mut u32 as *const u32
         };
         unsafe {
             __trap(&meta);
         }
+        elapsed
     }
 
     pub fn disable_rt_mode() {
         let meta = TrapMeta {
             id: TrapReason::DisableRt,
-            payload: 0x0 as *const u32
+            payload: core::ptr::null()
         };
         unsafe {
             __trap(&meta);
@@ -93,20 +116,355 @@ pub mod call_api {
         }
     }
 
+    ///
+    /// Blocks the calling task until the given interrupt line fires.
+    /// Lines the kernel has a handler for get refused, the call returns
+    /// right away. Otherwise the line gets masked after each event and
+    /// unmasked by the next call, occurrences in between get merged
+    /// into one. Only pulse or edge triggered sources are supported, the
+    /// task can not clear a source and a level which stays active
+    /// would end every wait right away.
+    /// # Arguments
+    /// * `irq` - An u32 value, position of the interrupt within the nvic
+    ///
+    pub fn wait_for_irq(irq: u32) {
+        let meta = TrapMeta {
+            id: TrapReason::WaitIrq,
+            payload: &irq as *const u32
+        };
+        unsafe {
+            __trap(&meta);
+        }
+    }
+
+    ///
+    /// Returns the maximum amount of bytes the task with the given pid
+    /// ever used on its stack, or None if there is no such task.
+    /// Services which return a value write it into the last payload word.
+    /// # Arguments
+    /// * `pid` - An u32 value, number of the task to measure
+    ///
+    pub fn stack_usage(pid: u32) -> Option<u32> {
+        let mut args: [u32; 2] = [pid, 0];
+        let meta = TrapMeta {
+            id: TrapReason::StackUsage,
+            payload: args.as_mut_ptr() as *const u32
+        };
+        unsafe {
+            __trap(&meta);
+        }
+        match args[1] {
+            u32::MAX => None,
+            used => Some(used),
+        }
+    }
+
+    ///
+    /// Requests a block of the kernel heap. Used by the global
+    /// allocator when running in a task, so prefer `alloc::boxed::Box`
+    /// or `alloc::vec::Vec` over calling this directly.
+    /// Returns the adress of the block or 0 if the heap is exhausted.
+    ///
+    pub fn heap_alloc(size: u32, align: u32) -> u32 {
+        let mut args: [u32; 3] = [size, align, 0];
+        let meta = TrapMeta {
+            id: TrapReason::HeapAlloc,
+            payload: args.as_mut_ptr() as *const u32
+        };
+        unsafe {
+            __trap(&meta);
+        }
+        args[2]
+    }
+
+    ///
+    /// Returns a block obtained by `heap_alloc` to the kernel heap.
+    /// Returns false if the block is unknown to the heap or got
+    /// freed already, the heap stays untouched then.
+    ///
+    pub fn heap_free(block: u32) -> bool {
+        let mut args: [u32; 2] = [block, 0];
+        let meta = TrapMeta {
+            id: TrapReason::HeapFree,
+            payload: args.as_mut_ptr() as *const u32
+        };
+        unsafe {
+            __trap(&meta);
+        }
+        unsafe { core::ptr::read_volatile(&args[1]) != 0 }
+    }
+
+    ///
+    /// Creates a pool of `blocks` equal sized blocks, which can be
+    /// allocated and freed in constant time. Returns the id of the
+    /// pool or None if the kernel is out of memory or pools.
+    ///
+    pub fn pool_create(block_size: u32, blocks: u32) -> Option<u32> {
+        let mut args: [u32; 3] = [block_size, blocks, 0];
+        let meta = TrapMeta {
+            id: TrapReason::PoolCreate,
+            payload: args.as_mut_ptr() as *const u32
+        };
+        unsafe {
+            __trap(&meta);
+        }
+        match args[2] {
+            u32::MAX => None,
+            id => Some(id),
+        }
+    }
+
+    fn pool_request(pool: u32, timeout: u32) -> Option<u32> {
+        let mut args: [u32; 3] = [pool, timeout, 0];
+        let meta = TrapMeta {
+            id: TrapReason::PoolAlloc,
+            payload: args.as_mut_ptr() as *const u32
+        };
+        unsafe {
+            __trap(&meta);
+        }
+        // a blocked task gets resumed after the kernel wrote the result
+        let block = unsafe { core::ptr::read_volatile(&args[2]) };
+        match block {
+            0 => None,
+            b => Some(b),
+        }
+    }
+
+    ///
+    /// Takes a block of the pool, returns None if it is empty.
+    ///
+    pub fn pool_try_alloc(pool: u32) -> Option<u32> {
+        pool_request(pool, 0)
+    }
+
+    ///
+    /// Takes a block of the pool, waits until one gets freed if it is empty.
+    /// Returns None only if the pool does not exist.
+    ///
+    pub fn pool_alloc(pool: u32) -> Option<u32> {
+        pool_request(pool, u32::MAX)
+    }
+
+    ///
+    /// Takes a block of the pool, waits at most `ticks` scheduler
+    /// periods if it is empty. Returns None on timeout.
+    ///
+    pub fn pool_alloc_timeout(pool: u32, ticks: u32) -> Option<u32> {
+        match ticks {
+            // 0 and u32::MAX have a special meaning for the kernel
+            0 => pool_try_alloc(pool),
+            t => pool_request(pool, t.min(u32::MAX - 1)),
+        }
+    }
+
+    ///
+    /// Returns a block to its pool. Returns false if
+    /// the block does not belong to the pool.
+    ///
+    pub fn pool_free(pool: u32, block: u32) -> bool {
+        let mut args: [u32; 3] = [pool, block, 0];
+        let meta = TrapMeta {
+            id: TrapReason::PoolFree,
+            payload: args.as_mut_ptr() as *const u32
+        };
+        unsafe {
+            __trap(&meta);
+        }
+        args[2] != 0
+    }
+
+    // edges of `wait_for_edge`, may be combined
+    pub const EDGE_RISING: u32 = 0b01;
+    pub const EDGE_FALLING: u32 = 0b10;
+
+    ///
+    /// Blocks the calling task until the pin sees one of the given edges.
+    /// Edges within `debounce` scheduler periods after the last reported
+    /// one get dropped, e.g. while a button bounces. Only edges after the
+    /// call count, those which occur while no task waits get lost.
+    /// Returns false without blocking if the pin does not exist, another
+    /// task or a driver took it or its external interrupt line already
+    /// serves the same pin of another port.
+    /// # Arguments
+    /// * `port` - A char, 'A' to 'F'
+    /// * `pin` - An u32 value, 0 to 15
+    /// * `edges` - EDGE_RISING, EDGE_FALLING or both
+    /// * `debounce` - An u32 value, in scheduler periods
+    ///
+    pub fn wait_for_edge(port: char, pin: u32, edges: u32, debounce: u32) -> bool {
+        let mut args: [u32; 5] = [port as u32, pin, edges, debounce, 0];
+        let meta = TrapMeta {
+            id: TrapReason::WaitEdge,
+            payload: args.as_mut_ptr() as *const u32
+        };
+        unsafe {
+            __trap(&meta);
+        }
+        unsafe { core::ptr::read_volatile(&args[4]) != 0 }
+    }
+
+    // modes of `gpio_claim`
+    pub const GPIO_INPUT: u32 = 0;
+    pub const GPIO_INPUT_PULLUP: u32 = 1;
+    pub const GPIO_INPUT_PULLDOWN: u32 = 2;
+    pub const GPIO_OUTPUT: u32 = 3;
+    pub const GPIO_OUTPUT_OPEN_DRAIN: u32 = 4;
+
+    fn gpio_request(id: TrapReason, port: char, pin: u32, value: u32) -> u32 {
+        let mut args: [u32; 4] = [port as u32, pin, value, 0];
+        let meta = TrapMeta {
+            id,
+            payload: args.as_mut_ptr() as *const u32
+        };
+        unsafe {
+            __trap(&meta);
+        }
+        args[3]
+    }
+
+    ///
+    /// Reserves a pin for the calling task and sets its mode. Only the
+    /// owner can use the pin until it terminates. Returns false if the
+    /// pin belongs to another task, a driver or does not exist.
+    /// # Arguments
+    /// * `port` - A char, 'A' to 'F'
+    /// * `pin` - An u32 value, 0 to 15
+    /// * `mode` - One of the GPIO_* modes
+    ///
+    pub fn gpio_claim(port: char, pin: u32, mode: u32) -> bool {
+        gpio_request(TrapReason::GpioClaim, port, pin, mode) != 0
+    }
+
+    ///
+    /// Drives a claimed pin high or low. Returns false if the
+    /// calling task does not own the pin.
+    ///
+    pub fn gpio_write(port: char, pin: u32, high: bool) -> bool {
+        gpio_request(TrapReason::GpioWrite, port, pin, high as u32) != 0
+    }
+
+    ///
+    /// Level of a claimed pin, None if the calling task does not own it.
+    ///
+    pub fn gpio_read(port: char, pin: u32) -> Option<bool> {
+        match gpio_request(TrapReason::GpioRead, port, pin, 0) {
+            u32::MAX => None,
+            level => Some(level != 0),
+        }
+    }
+
+    ///
+    /// Inverts the output of a claimed pin. Returns false if the
+    /// calling task does not own the pin.
+    ///
+    pub fn gpio_toggle(port: char, pin: u32) -> bool {
+        gpio_request(TrapReason::GpioToggle, port, pin, 0) != 0
+    }
+
+    ///
+    /// Sets the duty cycle of a pwm channel the kernel configured,
+    /// see dev::pwm. Returns false if the channel is not a pwm output.
+    /// # Arguments
+    /// * `timer` - An u32 value, the number of the timer, e.g. 4 for tim4
+    /// * `channel` - An u32 value, 1 to 4
+    /// * `duty` - An u32 value, in per mille of the period
+    ///
+    pub fn pwm_set_duty(timer: u32, channel: u32, duty: u32) -> bool {
+        let mut args: [u32; 4] = [timer, channel, duty, 0];
+        let meta = TrapMeta {
+            id: TrapReason::PwmDuty,
+            payload: args.as_mut_ptr() as *const u32
+        };
+        unsafe {
+            __trap(&meta);
+        }
+        args[3] != 0
+    }
+
+    fn capture_request(id: TrapReason, timer: u32, channel: u32) -> u32 {
+        let mut args: [u32; 3] = [timer, channel, 0];
+        let meta = TrapMeta {
+            id,
+            payload: args.as_mut_ptr() as *const u32
+        };
+        unsafe {
+            __trap(&meta);
+        }
+        unsafe { core::ptr::read_volatile(&args[2]) }
+    }
+
+    ///
+    /// Length of the last high pulse on an input capture channel in
+    /// microseconds, see dev::capture. None if the kernel did not
+    /// configure the channel for both edges or no pulse ended yet.
+    /// # Arguments
+    /// * `timer` - An u32 value, the number of the timer, e.g. 4 for tim4
+    /// * `channel` - An u32 value, 1 to 4
+    ///
+    pub fn capture_pulse_width(timer: u32, channel: u32) -> Option<u32> {
+        match capture_request(TrapReason::CaptureWidth, timer, channel) {
+            u32::MAX => None,
+            width => Some(width),
+        }
+    }
+
+    ///
+    /// Frequency of the signal on an input capture channel in hertz,
+    /// None if the channel did not capture two periods yet.
+    ///
+    pub fn capture_frequency(timer: u32, channel: u32) -> Option<u32> {
+        match capture_request(TrapReason::CaptureFrequency, timer, channel) {
+            u32::MAX => None,
+            frequency => Some(frequency),
+        }
+    }
+
+    ///
+    /// Blocks the calling task until the channel captures an edge. Only
+    /// captures after the call count, the earlier ones can still be
+    /// read through `capture_pulse_width` and `capture_frequency`.
+    /// Returns false without blocking if the kernel did not configure
+    /// the channel.
+    ///
+    pub fn capture_wait(timer: u32, channel: u32) -> bool {
+        capture_request(TrapReason::CaptureWait, timer, channel) != 0
+    }
+
     pub fn yield_task() {
         let meta = TrapMeta {
             id: TrapReason::YieldTask,
-            payload: 0x0 as *const u32
+            payload: core::ptr::null()
+        };
+        unsafe {
+            __trap(&meta);
+        }
+    }
+
+    ///
+    /// Stops the whole system with the given status. Under qemu the
+    /// emulator exits with it, e.g. to report the result of a test.
+    /// A board halts, so this call never returns. Only test images
+    /// built with `semihosting-exit` have it.
+    ///
+    #[cfg(feature = "semihosting-exit")]
+    pub fn exit(code: u32) -> ! {
+        let meta = TrapMeta {
+            id: TrapReason::Exit,
+            payload: &code as *const u32
         };
         unsafe {
             __trap(&meta);
         }
+        // the kernel does not come back, the trap only lacks the `!`
+        #[allow(clippy::empty_loop, reason = "never reached, the system ends within the trap")]
+        loop {}
     }
 
     pub fn terminate(){
         let meta = TrapMeta {
             id: TrapReason::TerminateTask,
-            payload: 0x0 as *const u32
+            payload: core::ptr::null()
         };
         unsafe {
             __trap(&meta);
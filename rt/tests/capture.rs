@@ -0,0 +1,133 @@
+//!
+//! Input capture on the timer channels. The capture slots are shared
+//! between the test threads, so each test uses a channel of its own.
+//! The timers count with 1 MHz, the board clock runs at 8 MHz.
+//!
+use rt::dev::capture;
+use rt::dev::timer::{Edge, TimerX};
+use rt::generic::cpu::c_registers::scb;
+use rt::generic::platform::registers::tim::{self, Tim};
+use rt::hal::fake;
+use rt::interrupts::irq;
+use rt::sched::task_control;
+use rt::sys::call_api;
+
+// lets the dispatcher run as if the timer raised `flags`
+fn raise(t: Tim, irq: usize, flags: u32, ccr: Option<(u8, u32)>) {
+    fake::set(t.sr().adress(), flags);
+    if let Some((channel, value)) = ccr {
+        fake::set(t.ccr(channel).adress(), value);
+    }
+    fake::set(scb::ICSR.adress(), 16 + irq as u32);
+    irq::dispatch();
+}
+
+fn capture(t: Tim, channel: u8, value: u32) {
+    raise(t, t.cc_irq, tim::cc(channel).mask(), Some((channel, value)));
+}
+
+#[test]
+fn channel_gets_edge_filter_and_divider() {
+    fake::reset();
+    let timer = TimerX::new(tim::TIM4);
+    assert!(timer.set_input_capture(2, Edge::Falling, 3, 4));
+    assert_eq!(tim::TIM4.ccmr(2).read(), (0b01 << 8) | (0b10 << 10) | (3 << 12));
+    assert_eq!(tim::TIM4.ccer().read(), 0b0010 << 4);
+    timer.set_capture_edge(2, Edge::Both);
+    assert_eq!(tim::TIM4.ccer().read(), 0b1010 << 4);
+
+    assert!(!timer.set_input_capture(2, Edge::Rising, 3, 3));
+    assert!(!timer.set_input_capture(2, Edge::Rising, 16, 1));
+    assert!(!timer.set_input_capture(5, Edge::Rising, 0, 1));
+}
+
+#[test]
+fn both_edges_give_width_and_frequency() {
+    fake::reset();
+    assert!(capture::init(tim::TIM4, 7));
+    assert!(capture::configure(tim::TIM4, 1, Edge::Both, 0, 1));
+    assert!(tim::TIM4.ccer().is_set(tim::cc_enable(1)));
+    assert!(!tim::TIM4.ccer().is_set(tim::cc_polarity(1)));
+
+    capture(tim::TIM4, 1, 100);
+    // waits for the falling edge next
+    assert!(tim::TIM4.ccer().is_set(tim::cc_polarity(1)));
+    assert_eq!(capture::pulse_width(4, 1), None);
+    capture(tim::TIM4, 1, 600);
+    assert!(!tim::TIM4.ccer().is_set(tim::cc_polarity(1)));
+    assert_eq!(capture::pulse_width(4, 1), Some(500));
+    assert_eq!(capture::frequency(4, 1), None);
+
+    capture(tim::TIM4, 1, 1_100);
+    // the last pulse ended with the capture at 600
+    assert_eq!(call_api::capture_pulse_width(4, 1), Some(500));
+    assert_eq!(call_api::capture_frequency(4, 1), Some(1_000));
+}
+
+#[test]
+fn overflows_extend_the_counter() {
+    fake::reset();
+    capture::init(tim::TIM15, 7);
+    assert!(capture::configure(tim::TIM15, 1, Edge::Rising, 0, 1));
+
+    capture(tim::TIM15, 1, 65_000);
+    // the overflow is still pending while the capture gets handled
+    raise(tim::TIM15, tim::TIM15.irq, tim::UIF.mask() | tim::cc(1).mask(), Some((1, 464)));
+    assert_eq!(capture::frequency(15, 1), Some(1_000));
+
+    raise(tim::TIM15, tim::TIM15.irq, tim::UIF.mask(), None);
+    capture(tim::TIM15, 1, 464);
+    assert_eq!(capture::frequency(15, 1), Some(15));
+    // a single edge carries no pulse width
+    assert_eq!(capture::pulse_width(15, 1), None);
+}
+
+#[test]
+fn divider_counts_several_periods() {
+    fake::reset();
+    capture::init(tim::TIM16, 7);
+    assert!(!capture::configure(tim::TIM16, 1, Edge::Both, 0, 4));
+    assert!(capture::configure(tim::TIM16, 1, Edge::Rising, 0, 4));
+    capture(tim::TIM16, 1, 1_000);
+    capture(tim::TIM16, 1, 2_000);
+    assert_eq!(capture::frequency(16, 1), Some(4_000));
+}
+
+#[test]
+fn waiting_needs_a_configured_channel() {
+    fake::reset();
+    assert!(!call_api::capture_wait(4, 4));
+    assert!(!call_api::capture_wait(5, 1));
+    assert_eq!(call_api::capture_frequency(4, 4), None);
+
+    task_control::reset();
+    task_control::insert(0x100);
+    task_control::next_process();
+    assert!(capture::init(tim::TIM17, 7));
+    // no update interrupt as long as no channel handles it
+    assert!(!tim::TIM17.dier().is_set(tim::UIE));
+    // invalid arguments leave the timer alone
+    assert!(!capture::configure(tim::TIM17, 2, Edge::Falling, 0, 1));
+    assert!(!capture::configure(tim::TIM17, 1, Edge::Falling, 16, 1));
+    assert!(fake::writes_to(tim::TIM17.ccmr(1).adress()).is_empty());
+    assert!(!tim::TIM17.dier().is_set(tim::UIE));
+    assert!(capture::configure(tim::TIM17, 1, Edge::Falling, 0, 1));
+    assert!(tim::TIM17.dier().is_set(tim::UIE));
+    raise(tim::TIM17, tim::TIM17.irq, tim::UIF.mask(), None);
+    assert!(!tim::TIM17.sr().is_set(tim::UIF));
+    capture(tim::TIM17, 1, 10);
+    // the capture before waiting is stale, the task blocks
+    assert!(call_api::capture_wait(17, 1));
+    assert!(task_control::wake_waiting(capture::event_of(17, 1).unwrap()));
+}
+
+#[test]
+fn kernel_timers_are_refused() {
+    fake::reset();
+    assert!(!capture::init(tim::TIM2, 7));
+    assert!(!capture::init(tim::TIM3, 7));
+    assert!(!capture::configure(tim::TIM2, 1, Edge::Rising, 0, 1));
+    assert!(fake::writes_to(tim::TIM3.cr1().adress()).is_empty());
+    assert_eq!(capture::event_of(2, 1), None);
+}
+
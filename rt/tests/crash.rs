@@ -0,0 +1,54 @@
+//!
+//! Crash record of the panic handler, printed after the reset.
+//! The record is a single static, so every test holds the lock.
+//!
+use rt::fault::crash;
+use rt::generic::platform::registers::usart::{self, USART1};
+use rt::hal::fake;
+use std::sync::{Mutex, MutexGuard};
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+const HEADER: &str = "---- CRASH RECORD OF LAST RUN ----\n\r";
+const FOOTER: &str = "\n\r----------------------------------\n\r";
+
+fn setup() -> MutexGuard<'static, ()> {
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    fake::reset();
+    fake::set(USART1.isr().adress(), usart::TXE.mask());
+    guard
+}
+
+fn transmitted() -> String {
+    fake::writes_to(USART1.tdr().adress()).iter().map(|c| *c as u8 as char).collect()
+}
+
+#[test]
+fn stored_record_gets_printed_once() {
+    let _guard = setup();
+    crash::save(&"panicked at src/main.rs:7:5:\nboom");
+    crash::print_last();
+    assert_eq!(transmitted(), format!("{}panicked at src/main.rs:7:5:\nboom{}", HEADER, FOOTER));
+
+    fake::reset();
+    fake::set(USART1.isr().adress(), usart::TXE.mask());
+    crash::print_last();
+    assert_eq!(transmitted(), "");
+}
+
+#[test]
+fn long_message_gets_cut_off() {
+    let _guard = setup();
+    crash::save(&"x".repeat(300));
+    crash::print_last();
+    assert_eq!(transmitted(), format!("{}{}{}", HEADER, "x".repeat(192), FOOTER));
+}
+
+#[test]
+fn cut_within_a_character_drops_it() {
+    let _guard = setup();
+    // the two bytes of the umlaut start at the last byte of the record
+    crash::save(&format!("{}ä", "x".repeat(191)));
+    crash::print_last();
+    assert_eq!(transmitted(), format!("{}{}{}", HEADER, "x".repeat(191), FOOTER));
+}
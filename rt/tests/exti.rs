@@ -0,0 +1,76 @@
+//!
+//! External interrupt lines, armed through the edge syscall
+//! and signalled by the exti interrupts.
+//!
+use rt::dev::gpio::gpiod;
+use rt::dev::{exti, gpio_owner};
+use rt::generic::cpu::c_registers::scb;
+use rt::generic::platform::irqn;
+use rt::generic::platform::registers::{exti as exti_regs, syscfg};
+use rt::hal::fake;
+use rt::interrupts::irq;
+use rt::sched::task_control;
+use rt::sys::call_api;
+
+// lets the dispatcher run as if `irq` was the active interrupt
+fn raise(irq: usize, line: u8) {
+    fake::set(exti_regs::PR.adress(), exti_regs::line(line).mask());
+    fake::set(scb::ICSR.adress(), 16 + irq as u32);
+    irq::dispatch();
+}
+
+#[test]
+fn arming_selects_port_and_edges() {
+    fake::reset();
+    assert!(exti::arm('C' as u32, 6, exti::FALLING, 0, 0).is_some());
+    assert_eq!(syscfg::exticr(6).read(), 2 << 8);
+    assert!(exti_regs::IMR.is_set(exti_regs::line(6)));
+    assert!(exti_regs::FTSR.is_set(exti_regs::line(6)));
+    assert!(!exti_regs::RTSR.is_set(exti_regs::line(6)));
+}
+
+#[test]
+fn line_serves_one_port_only() {
+    fake::reset();
+    assert!(exti::arm('B' as u32, 7, exti::RISING, 0, 0).is_some());
+    assert!(exti::arm('D' as u32, 7, exti::RISING, 0, 0).is_none());
+    assert!(exti::arm('G' as u32, 1, exti::RISING, 0, 0).is_none());
+    assert!(exti::arm('A' as u32, 16, exti::RISING, 0, 0).is_none());
+    assert!(exti::arm('A' as u32, 2, 0, 0, 0).is_none());
+}
+
+#[test]
+fn interrupt_clears_pending_line() {
+    fake::reset();
+    exti::arm('A' as u32, 12, exti::RISING, 0, 0).unwrap();
+    raise(irqn::EXTI15_10, 12);
+    assert_eq!(fake::writes_to(exti_regs::PR.adress()).last(), Some(&exti_regs::line(12).mask()));
+}
+
+#[test]
+fn edges_before_waiting_get_dropped() {
+    fake::reset();
+    task_control::reset();
+    task_control::insert(0x100);
+    task_control::next_process();
+    let flag = exti::arm('A' as u32, 0, exti::RISING, 0, 0).unwrap();
+    // nobody waits for this edge, it must not end the next wait
+    raise(irqn::EXTI0, 0);
+    assert!(call_api::wait_for_edge('A', 0, call_api::EDGE_RISING, 0));
+    assert!(task_control::wake_waiting(flag));
+    assert!(!call_api::wait_for_edge('A', 16, call_api::EDGE_RISING, 0));
+}
+
+#[test]
+fn pins_of_others_cannot_be_armed() {
+    fake::reset();
+    assert!(gpio_owner::claim('E' as u32, 9, gpio_owner::INPUT, 1));
+    assert!(exti::arm('E' as u32, 9, exti::RISING, 0, 0).is_none());
+    assert!(!call_api::wait_for_edge('E', 9, call_api::EDGE_RISING, 0));
+    assert!(exti::arm('E' as u32, 9, exti::RISING, 0, 1).is_some());
+    gpio_owner::release_all(1);
+
+    // a pin of a driver is off limits as well
+    let _pin = gpiod::PD10::take().unwrap();
+    assert!(exti::arm('D' as u32, 10, exti::RISING, 0, 0).is_none());
+}
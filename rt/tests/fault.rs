@@ -0,0 +1,69 @@
+//!
+//! Causes decoded from sample values of the fault status registers.
+//!
+use rt::fault::status;
+use rt::generic::cpu::c_registers::scb;
+use rt::generic::platform::registers::usart::{self, USART1};
+use rt::hal::fake;
+
+fn causes(cfsr: u32, hfsr: u32) -> String {
+    fake::reset();
+    fake::set(USART1.isr().adress(), usart::TXE.mask());
+    fake::set(scb::CFSR.adress(), cfsr);
+    fake::set(scb::HFSR.adress(), hfsr);
+    fake::set(scb::MMFAR.adress(), 0x2000_0100);
+    fake::set(scb::BFAR.adress(), 0x4800_0000);
+    status::print_causes();
+    fake::writes_to(USART1.tdr().adress()).iter().map(|c| *c as u8 as char).collect()
+}
+
+#[test]
+fn memmanage_fault_with_adress() {
+    assert_eq!(causes(0x0000_0082, 0), "  cause: data access violation\n\r  mmfar: 0x20000100\n\r");
+    // the adress is only valid if the cpu says so
+    assert_eq!(causes(0x0000_0001, 0), "  cause: instruction access violation\n\r");
+}
+
+#[test]
+fn bus_fault_with_adress() {
+    assert_eq!(causes(0x0000_8200, 0), "  cause: precise data bus error\n\r  bfar: 0x48000000\n\r");
+    assert_eq!(causes(0x0000_0400, 0), "  cause: imprecise data bus error\n\r");
+}
+
+#[test]
+fn usage_faults() {
+    assert_eq!(causes(0x0200_0000, 0), "  cause: divide by zero\n\r");
+    assert_eq!(causes(0x0001_0000, 0), "  cause: undefined instruction\n\r");
+    assert_eq!(
+        causes(0x0102_0000, 0),
+        "  cause: invalid state (thumb bit cleared)\n\r  cause: unaligned access\n\r"
+    );
+}
+
+#[test]
+fn escalated_hardfault() {
+    assert_eq!(
+        causes(0x0008_0000, 0x4000_0000),
+        "  cause: coprocessor access while disabled\n\r  cause: escalated to hardfault\n\r"
+    );
+    assert_eq!(causes(0, 0x0000_0002), "  cause: bus fault on vector table read\n\r");
+    assert_eq!(causes(0, 0), "");
+}
+
+#[test]
+fn stacking_errors_lose_the_frame() {
+    assert_eq!(causes(0x0000_0010, 0), "  cause: stack overflow (mpu fault on exception entry)\n\r");
+    assert!(!status::frame_stacked());
+    assert_eq!(causes(0x0000_1000, 0), "  cause: stack overflow (bus fault on exception entry)\n\r");
+    assert!(!status::frame_stacked());
+    causes(0x0000_0008, 0);
+    assert!(status::frame_stacked());
+}
+
+#[test]
+fn clearing_writes_the_flags_back() {
+    causes(0x0000_0082, 0x4000_0000);
+    status::clear();
+    assert_eq!(fake::writes_to(scb::CFSR.adress()), vec![0x0000_0082]);
+    assert_eq!(fake::writes_to(scb::HFSR.adress()), vec![0x4000_0000]);
+}
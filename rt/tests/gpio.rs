@@ -0,0 +1,67 @@
+//!
+//! Register accesses of the gpio driver.
+//!
+use rt::dev::gpio_driver::GpioX;
+use rt::dev::gpio_types::{InputState, ModerTypes, PullTypes, SpeedTypes};
+use rt::generic::platform::adresses;
+use rt::generic::platform::registers::gpio::Gpio;
+use rt::hal::fake;
+
+const PORT_E: Gpio = Gpio::at(adresses::GPIOE);
+
+#[test]
+fn unknown_ports_and_pins_are_rejected() {
+    assert!(GpioX::new("D", 0).is_some());
+    assert!(GpioX::new("F", 15).is_some());
+    assert!(GpioX::new("G", 0).is_none());
+    assert!(GpioX::new("A", 16).is_none());
+}
+
+#[test]
+fn mode_replaces_previous_one() {
+    fake::reset();
+    let pin = GpioX::new("E", 11).unwrap();
+    pin.set_moder(ModerTypes::AnalogMode);
+    pin.set_moder(ModerTypes::GeneralPurposeOutputMode);
+    assert_eq!(PORT_E.moder().read(), 0b01 << 22);
+}
+
+#[test]
+fn pull_and_speed_use_two_bits_per_pin() {
+    fake::reset();
+    fake::set(PORT_E.pupdr().adress(), 0b11 << 22);
+    let pin = GpioX::new("E", 12).unwrap();
+    pin.set_pupdr(PullTypes::PullDown);
+    pin.set_ospeedr(SpeedTypes::High);
+    assert_eq!(PORT_E.pupdr().read(), (0b11 << 22) | (0b10 << 24));
+    assert_eq!(PORT_E.ospeedr().read(), 0b11 << 24);
+}
+
+#[test]
+fn set_and_reset_write_bsrr_only() {
+    fake::reset();
+    let pin = GpioX::new("E", 13).unwrap();
+    pin.set();
+    pin.reset();
+    assert_eq!(fake::writes_to(PORT_E.bsrr().adress()), vec![1 << 13, 1 << 29]);
+    assert!(fake::writes_to(PORT_E.odr().adress()).is_empty());
+}
+
+#[test]
+fn toggle_inverts_output() {
+    fake::reset();
+    let pin = GpioX::new("E", 14).unwrap();
+    pin.toggle();
+    fake::set(PORT_E.odr().adress(), 1 << 14);
+    pin.toggle();
+    assert_eq!(fake::writes_to(PORT_E.bsrr().adress()), vec![1 << 14, 1 << 30]);
+}
+
+#[test]
+fn reads_input_level() {
+    fake::reset();
+    let pin = GpioX::new("E", 11).unwrap();
+    assert!(matches!(pin.read_idr(), InputState::Low));
+    fake::set(PORT_E.idr().adress(), 1 << 11);
+    assert!(matches!(pin.read_idr(), InputState::High));
+}
@@ -0,0 +1,57 @@
+//!
+//! Pins claimed through the gpio syscalls. On the host every
+//! syscall comes from the task with pid 0.
+//!
+use rt::dev::gpio_owner;
+use rt::generic::platform::adresses;
+use rt::generic::platform::registers::gpio::Gpio;
+use rt::hal::fake;
+use rt::sys::call_api;
+
+#[test]
+fn claimed_output_gets_driven() {
+    fake::reset();
+    let port = Gpio::at(adresses::GPIOB);
+    assert!(call_api::gpio_claim('B', 3, call_api::GPIO_OUTPUT));
+    assert_eq!(port.moder().read(), 0b01 << 6);
+    assert!(call_api::gpio_write('B', 3, true));
+    assert!(call_api::gpio_toggle('B', 3));
+    assert_eq!(fake::writes_to(port.bsrr().adress()), vec![1 << 3, 1 << 3]);
+}
+
+#[test]
+fn claimed_input_gets_read() {
+    fake::reset();
+    let port = Gpio::at(adresses::GPIOB);
+    assert!(call_api::gpio_claim('B', 4, call_api::GPIO_INPUT_PULLUP));
+    assert_eq!(port.pupdr().read(), 0b01 << 8);
+    fake::set(port.idr().adress(), 1 << 4);
+    assert_eq!(call_api::gpio_read('B', 4), Some(true));
+}
+
+#[test]
+fn pins_of_other_tasks_are_refused() {
+    fake::reset();
+    assert!(gpio_owner::claim('C' as u32, 5, gpio_owner::OUTPUT, 1));
+    assert!(!call_api::gpio_claim('C', 5, call_api::GPIO_OUTPUT));
+    assert!(!call_api::gpio_write('C', 5, true));
+    assert_eq!(call_api::gpio_read('C', 5), None);
+    // free again once the owner terminated
+    gpio_owner::release_all(1);
+    assert!(call_api::gpio_claim('C', 5, call_api::GPIO_OUTPUT));
+}
+
+#[test]
+fn unknown_pins_and_modes_are_refused() {
+    assert!(!call_api::gpio_claim('G', 0, call_api::GPIO_OUTPUT));
+    assert!(!call_api::gpio_claim('B', 16, call_api::GPIO_OUTPUT));
+    assert!(!call_api::gpio_claim('B', 5, 99));
+    assert!(!call_api::gpio_toggle('B', 6));
+}
+
+#[test]
+fn console_pin_cannot_be_claimed() {
+    fake::reset();
+    rt::generic::board::init();
+    assert!(!call_api::gpio_claim('A', 9, call_api::GPIO_OUTPUT));
+}
@@ -0,0 +1,55 @@
+//!
+//! Type-state pins, each test thread takes different pins
+//! as the taken pins are shared between them.
+//!
+use rt::dev::gpio::{gpioa, gpiod, gpioe, PullUp, PushPull, AF7};
+use rt::generic::platform::adresses;
+use rt::generic::platform::registers::gpio::Gpio;
+use rt::hal::fake;
+
+#[test]
+fn pin_can_be_taken_once() {
+    let pin = gpiod::PD3::take();
+    assert!(pin.is_some());
+    assert!(gpiod::PD3::take().is_none());
+    // the same pin number of another port is still free
+    assert!(gpioe::PE3::take().is_some());
+}
+
+#[test]
+fn released_pin_can_be_taken_again() {
+    let pin = gpiod::PD4::take().unwrap().into_output::<PushPull>();
+    pin.release();
+    assert!(gpiod::PD4::take().is_some());
+}
+
+#[test]
+fn output_pin_drives_through_bsrr() {
+    fake::reset();
+    let port = Gpio::at(adresses::GPIOE);
+    let led = gpioe::PE9::take().unwrap().into_output::<PushPull>();
+    assert_eq!(port.moder().read(), 0b01 << 18);
+    led.set_high();
+    led.set_low();
+    assert_eq!(fake::writes_to(port.bsrr().adress()), vec![1 << 9, 1 << 25]);
+}
+
+#[test]
+fn input_pin_gets_pulled_up() {
+    fake::reset();
+    let port = Gpio::at(adresses::GPIOA);
+    let button = gpioa::PA0::take().unwrap().into_input::<PullUp>();
+    assert_eq!(port.moder().read(), 0);
+    assert_eq!(port.pupdr().read(), 0b01);
+    fake::set(port.idr().adress(), 0b1);
+    assert!(button.is_high());
+}
+
+#[test]
+fn alternate_function_gets_selected() {
+    fake::reset();
+    let port = Gpio::at(adresses::GPIOA);
+    gpioa::PA10::take().unwrap().into_alternate::<AF7>();
+    assert_eq!(port.afr(10).read(), 7 << 8);
+    assert_eq!(port.moder().read(), 0b10 << 20);
+}
@@ -0,0 +1,44 @@
+//!
+//! Frees of the kernel heap, which tasks reach through a syscall.
+//! The heap is shared by the test threads and the critical sections
+//! do nothing on the host, so the tests take turns.
+//!
+use rt::mem::heap;
+use rt::sys::call_api;
+use std::sync::Mutex;
+
+static TURN: Mutex<()> = Mutex::new(());
+
+#[test]
+fn block_can_be_freed_once() {
+    let _turn = TURN.lock().unwrap();
+    let block = heap::kernel_alloc(24, 8);
+    assert!(!block.is_null());
+    assert!(heap::kernel_free(block));
+    assert!(!heap::kernel_free(block));
+}
+
+#[test]
+fn foreign_pointers_get_refused() {
+    let _turn = TURN.lock().unwrap();
+    let block = heap::kernel_alloc(64, 8);
+    let used = heap::stats().used;
+    // within the payload, unaligned, below and beyond the heap
+    assert!(!heap::kernel_free(block.wrapping_add(16)));
+    assert!(!heap::kernel_free(block.wrapping_add(3)));
+    assert!(!heap::kernel_free(4 as *mut u8));
+    let (base, size) = heap::region();
+    assert!(!heap::kernel_free((base + size) as *mut u8));
+    assert_eq!(heap::stats().used, used);
+    assert!(heap::kernel_free(block));
+}
+
+// heap adresses of the host do not fit the 32 bit syscall arguments,
+// so only the refusals can be checked here
+#[test]
+fn syscall_reports_refused_free() {
+    let _turn = TURN.lock().unwrap();
+    assert!(!call_api::heap_free(0));
+    assert!(!call_api::heap_free(1));
+    assert!(!call_api::heap_free(0x2000_0000));
+}
@@ -0,0 +1,20 @@
+//!
+//! Interrupt lines tasks may wait on.
+//!
+use rt::generic::platform::irqn;
+use rt::interrupts::irq;
+
+fn handler() {}
+
+#[test]
+fn lines_of_the_kernel_cannot_be_armed() {
+    assert_eq!(irq::arm(irqn::COUNT), None);
+    irq::register_irq(irqn::TIM7, handler);
+    assert_eq!(irq::arm(irqn::TIM7), None);
+
+    let mask = irq::arm(irqn::TIM6_DAC).unwrap();
+    // arming again keeps the event flag
+    assert_eq!(irq::arm(irqn::TIM6_DAC), Some(mask));
+    irq::unregister_irq(irqn::TIM7);
+    assert!(irq::arm(irqn::TIM7).is_some());
+}
@@ -0,0 +1,137 @@
+//!
+//! Encoding of the mpu regions and the memory a task may access,
+//! which also limits the payloads the kernel accepts from it. The
+//! adresses of the task stacks and the heap get cut to 32 bits on
+//! the host, which keeps them consistent with each other. The task
+//! table is global, so the tests using it hold the lock.
+//!
+use rt::generic::config::{FLASH_SIZE, TASK_STACK_SIZE};
+use rt::generic::cpu::c_registers::mpu;
+use rt::generic::platform::adresses;
+use rt::hal::fake;
+use rt::mem::heap;
+use rt::mpu::mpu_handler::{self, GUARD_SIZE};
+use rt::sched::{process, task_control};
+use std::sync::{Mutex, MutexGuard};
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+// normal memory, write-back, shareable, never executed
+const SRAM: u32 = 0x1007_0000;
+
+fn setup() -> MutexGuard<'static, ()> {
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    task_control::reset();
+    guard
+}
+
+fn task(_: u32) {}
+
+#[test]
+fn size_field_is_log2_minus_one() {
+    // the guard region, the smallest region there is
+    assert_eq!(mpu_handler::rasr(32, SRAM, mpu::AP_NO_ACCESS), 0x1007_0009);
+    // task stacks
+    assert_eq!(mpu_handler::rasr(256, SRAM, mpu::AP_FULL_ACCESS), 0x1307_000F);
+    assert_eq!(mpu_handler::rasr(1024, SRAM, mpu::AP_FULL_ACCESS), 0x1307_0013);
+    assert_eq!(mpu_handler::rasr(2048, SRAM, mpu::AP_FULL_ACCESS), 0x1307_0015);
+    // the heap, privileged sram and flash
+    assert_eq!(mpu_handler::rasr(8192, SRAM, mpu::AP_FULL_ACCESS), 0x1307_0019);
+    assert_eq!(mpu_handler::rasr(64 * 1024, SRAM, mpu::AP_PRIV_RW), 0x1107_001F);
+    assert_eq!(mpu_handler::rasr(512 * 1024, mpu::C.mask(), mpu::AP_READ_ONLY), 0x0602_0025);
+}
+
+#[test]
+fn base_keeps_the_aligned_adress() {
+    assert_eq!(mpu_handler::rbar(0x2000_0800), 0x2000_0800);
+    assert_eq!(mpu_handler::rbar(0x2000_0020), 0x2000_0020);
+    assert_eq!(mpu_handler::rbar(0x0800_0000), 0x0800_0000);
+    // region number and valid bit are not used
+    assert_eq!(mpu_handler::rbar(0x2000_041F), 0x2000_0400);
+}
+
+#[test]
+fn switch_opens_the_stack_above_the_guard() {
+    let _guard = setup();
+    fake::reset();
+    fake::set(mpu::TYPE.adress(), 8 << 8);
+    process::create(task as *const () as u32).unwrap();
+    let pid = process::create(task as *const () as u32).unwrap();
+    let (base, size) = process::stack_region(pid).unwrap();
+    mpu_handler::switch_to(pid);
+
+    assert_eq!(fake::writes_to(mpu::RNR.adress()), vec![3, 4]);
+    assert_eq!(fake::writes_to(mpu::RBAR.adress()), vec![base & !0x1F; 2]);
+    assert_eq!(
+        fake::writes_to(mpu::RASR.adress()),
+        vec![
+            mpu_handler::rasr(size, SRAM, mpu::AP_FULL_ACCESS),
+            mpu_handler::rasr(GUARD_SIZE, SRAM, mpu::AP_NO_ACCESS)
+        ]
+    );
+}
+
+#[test]
+fn init_sets_up_the_shared_regions() {
+    let _guard = setup();
+    fake::reset();
+    fake::set(mpu::TYPE.adress(), 8 << 8);
+    mpu_handler::init();
+    let (heap_base, heap_size) = heap::region();
+
+    assert_eq!(fake::writes_to(mpu::RNR.adress()), vec![0, 1, 2]);
+    assert_eq!(
+        fake::writes_to(mpu::RBAR.adress()),
+        vec![adresses::FLASH, adresses::SRAM, heap_base & !0x1F]
+    );
+    let flash = fake::writes_to(mpu::RASR.adress())[0];
+    assert_eq!(flash & !mpu::SIZE.mask(), 0x0602_0001);
+    // 2^(n+1) bytes
+    assert_eq!(2 << ((flash & mpu::SIZE.mask()) >> 1), (FLASH_SIZE as u32).next_power_of_two());
+    assert_eq!(fake::writes_to(mpu::RASR.adress())[2], mpu_handler::rasr(heap_size, SRAM, mpu::AP_FULL_ACCESS));
+    assert_eq!(fake::writes_to(mpu::CTRL.adress()), vec![0b101]);
+}
+
+#[test]
+fn no_regions_without_an_mpu() {
+    let _guard = setup();
+    fake::reset();
+    process::create(task as *const () as u32).unwrap();
+    mpu_handler::switch_to(0);
+    mpu_handler::init();
+    assert!(fake::writes_to(mpu::RASR.adress()).is_empty());
+    assert!(fake::writes_to(mpu::CTRL.adress()).is_empty());
+}
+
+#[test]
+fn payload_has_to_be_within_the_regions_of_the_task() {
+    let _guard = setup();
+    let first = process::create(task as *const () as u32).unwrap();
+    let second = process::create(task as *const () as u32).unwrap();
+    let (base, size) = process::stack_region(first).unwrap();
+    let top = base + size;
+
+    // its own stack above the guard region
+    assert!(mpu_handler::task_may_access(first, top - 12, 12, true));
+    assert!(mpu_handler::task_may_access(first, base + GUARD_SIZE, 4, true));
+    assert!(!mpu_handler::task_may_access(first, base, 4, true));
+    assert!(!mpu_handler::task_may_access(first, top - 8, 12, true));
+    assert_eq!(mpu_handler::accessible(first, top - 12, true), 12);
+    assert_eq!(size, TASK_STACK_SIZE as u32);
+
+    // not the stack of another task
+    let (other, _) = process::stack_region(second).unwrap();
+    assert!(!mpu_handler::task_may_access(first, other + GUARD_SIZE, 4, false));
+    assert!(mpu_handler::task_may_access(second, other + GUARD_SIZE, 4, false));
+
+    // the heap is shared, the flash is read only
+    let (heap_base, _) = heap::region();
+    assert!(mpu_handler::task_may_access(first, heap_base, 8, true));
+    assert!(mpu_handler::task_may_access(first, adresses::FLASH, 4, false));
+    assert!(!mpu_handler::task_may_access(first, adresses::FLASH, 4, true));
+    assert_eq!(mpu_handler::accessible(first, adresses::FLASH + 4, false), FLASH_SIZE as u32 - 4);
+
+    // neither the kernel nor the peripherals
+    assert!(!mpu_handler::task_may_access(first, 0x4000_0000, 4, false));
+    assert!(!mpu_handler::task_may_access(first, 0, 4, false));
+}
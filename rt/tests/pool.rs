@@ -0,0 +1,55 @@
+//!
+//! Fixed size block pools. Their storage comes from the kernel heap,
+//! which the test threads share, so the tests take turns.
+//!
+use rt::mem::pool;
+use std::sync::Mutex;
+
+static TURN: Mutex<()> = Mutex::new(());
+
+#[test]
+fn blocks_are_handed_out_in_order() {
+    let _turn = TURN.lock().unwrap();
+    let id = pool::create(12, 3).unwrap();
+    let first = pool::try_alloc(id).unwrap();
+    assert_eq!(pool::try_alloc(id), Some(first + 16));
+    assert_eq!(pool::try_alloc(id), Some(first + 32));
+    assert_eq!(pool::try_alloc(id), None);
+    assert_eq!(pool::available(id), Some(0));
+}
+
+#[test]
+fn block_can_be_freed_once() {
+    let _turn = TURN.lock().unwrap();
+    let id = pool::create(8, 2).unwrap();
+    let a = pool::try_alloc(id).unwrap();
+    let b = pool::try_alloc(id).unwrap();
+    assert!(pool::free(id, a));
+    assert!(!pool::free(id, a));
+    assert_eq!(pool::available(id), Some(1));
+    // a second free would have handed out the same block twice
+    assert_eq!(pool::try_alloc(id), Some(a));
+    assert_eq!(pool::try_alloc(id), None);
+    assert!(pool::free(id, b));
+}
+
+#[test]
+fn foreign_blocks_get_refused() {
+    let _turn = TURN.lock().unwrap();
+    let id = pool::create(8, 2).unwrap();
+    let a = pool::try_alloc(id).unwrap();
+    // never allocated, within a block, beyond the pool, unknown pool
+    assert!(!pool::free(id, a + 8));
+    assert!(!pool::free(id, a + 4));
+    assert!(!pool::free(id, a + 16));
+    assert!(!pool::free(id + 1, a));
+    assert!(pool::free(id, a));
+}
+
+#[test]
+fn pool_size_is_limited() {
+    let _turn = TURN.lock().unwrap();
+    assert!(pool::create(8, 0).is_none());
+    assert!(pool::create(0, 8).is_none());
+    assert!(pool::create(8, 257).is_none());
+}
@@ -0,0 +1,61 @@
+//!
+//! Task stacks, run on the host. The process table is global,
+//! so every test holds the lock and starts with an empty table.
+//!
+use rt::generic::config::{MAX_TASKS, TASK_STACK_SIZE};
+use rt::sched::{process, scheduler, task_control};
+use std::sync::{Mutex, MutexGuard};
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+fn setup() -> MutexGuard<'static, ()> {
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    task_control::reset();
+    guard
+}
+
+fn task() {}
+
+#[test]
+fn high_water_mark_follows_the_deepest_write() {
+    let _guard = setup();
+    let pid = process::create(task as *const () as u32).unwrap();
+    // only the initial frame of 16 registers
+    assert_eq!(process::stack_usage(pid), Some(64));
+
+    let stack = unsafe { process::stack_of(pid) }.unwrap();
+    let words = TASK_STACK_SIZE / 4;
+    stack[words - 40] = 0;
+    assert_eq!(process::stack_usage(pid), Some(40 * 4));
+    // writes above the deepest point do not change it
+    stack[words - 20] = 0;
+    assert_eq!(process::stack_usage(pid), Some(40 * 4));
+    assert!(process::canary_intact(pid));
+    assert_eq!(process::stack_usage(pid + 1), None);
+}
+
+#[test]
+fn overflow_overwrites_the_canary() {
+    let _guard = setup();
+    let first = process::create(task as *const () as u32).unwrap();
+    let second = process::create(task as *const () as u32).unwrap();
+    let stack = unsafe { process::stack_of(second) }.unwrap();
+    // the canary is the lowest word above the 32 byte guard region
+    stack[9] = 0;
+    assert!(process::canary_intact(second));
+    stack[8] = 0;
+    assert!(!process::canary_intact(second));
+    assert!(process::canary_intact(first));
+}
+
+#[test]
+fn last_stack_is_kept_for_the_idle_task() {
+    let _guard = setup();
+    for _ in 0..MAX_TASKS - 1 {
+        assert!(process::create(task as *const () as u32).is_some());
+    }
+    assert_eq!(process::create(task as *const () as u32), None);
+    scheduler::load();
+    assert_eq!(task_control::task_count() as usize, MAX_TASKS);
+    assert!(process::stack_region(MAX_TASKS as u32 - 1).is_some());
+}
@@ -0,0 +1,119 @@
+//!
+//! Pwm outputs on the timer channels, the board clock of
+//! the stm32f3 discovery runs the timers at 8 MHz.
+//!
+use rt::dev::gpio::gpiod;
+use rt::dev::pwm::{self, Polarity, Pwm};
+use rt::generic::platform::adresses;
+use rt::generic::platform::registers::gpio::Gpio;
+use rt::generic::platform::registers::tim;
+use rt::hal::fake;
+
+const PWM: Pwm = Pwm::new(tim::TIM4);
+
+#[test]
+fn frequency_uses_the_smallest_prescaler() {
+    fake::reset();
+    assert!(PWM.init(1_000));
+    assert_eq!(tim::TIM4.psc().read(), 0);
+    assert_eq!(tim::TIM4.arr().read(), 7_999);
+    assert!(tim::TIM4.cr1().is_set(tim::ARPE));
+    assert!(tim::TIM4.cr1().is_set(tim::CEN));
+
+    PWM.set_frequency(50);
+    assert_eq!(tim::TIM4.psc().read(), 2);
+    assert_eq!(tim::TIM4.arr().read(), 53_332);
+}
+
+#[test]
+fn wide_timer_needs_no_prescaler() {
+    fake::reset();
+    // tim2 is the only 32 bit timer of the f303. `init` refuses it as
+    // the kernel measures with it, so only the calculation gets checked.
+    Pwm::new(tim::TIM2).set_frequency(50);
+    assert_eq!(tim::TIM2.psc().read(), 0);
+    assert_eq!(tim::TIM2.arr().read(), 159_999);
+}
+
+#[test]
+fn kernel_timers_are_refused() {
+    fake::reset();
+    assert!(!Pwm::new(tim::TIM2).init(1_000));
+    assert!(!Pwm::new(tim::TIM3).init(1_000));
+    assert!(fake::writes_to(tim::TIM2.cr1().adress()).is_empty());
+    assert!(fake::writes_to(tim::TIM3.cr1().adress()).is_empty());
+}
+
+#[test]
+fn channel_gets_pwm_mode_and_polarity() {
+    fake::reset();
+    PWM.init(1_000);
+    PWM.enable(2, Polarity::ActiveLow);
+    assert_eq!(tim::TIM4.ccmr(2).read(), (0b110 << 12) | (1 << 11));
+    assert_eq!(tim::TIM4.ccer().read(), 0b11 << 4);
+    assert!(PWM.is_enabled(2));
+    assert!(!PWM.is_enabled(1));
+
+    PWM.disable(2);
+    assert!(!PWM.is_enabled(2));
+}
+
+#[test]
+fn duty_is_given_in_per_mille() {
+    fake::reset();
+    PWM.init(1_000);
+    PWM.set_duty(1, 250);
+    assert_eq!(tim::TIM4.ccr1().read(), 2_000);
+    assert_eq!(PWM.duty(1), 250);
+    PWM.set_duty(1, 5_000);
+    assert_eq!(tim::TIM4.ccr1().read(), 8_000);
+}
+
+#[test]
+fn duty_survives_a_new_frequency() {
+    fake::reset();
+    PWM.init(1_000);
+    PWM.set_duty(3, 500);
+    PWM.set_frequency(2_000);
+    assert_eq!(tim::TIM4.ccr(3).read(), 2_000);
+    assert_eq!(PWM.duty(3), 500);
+}
+
+#[test]
+fn complementary_outputs_need_an_advanced_timer() {
+    fake::reset();
+    assert!(!PWM.enable_complementary(1, Polarity::ActiveHigh));
+
+    let advanced = Pwm::new(tim::TIM1);
+    advanced.init(1_000);
+    assert!(tim::TIM1.bdtr().is_set(tim::MOE));
+    advanced.enable(1, Polarity::ActiveHigh);
+    assert!(advanced.enable_complementary(1, Polarity::ActiveLow));
+    assert_eq!(tim::TIM1.ccer().read(), 0b1101);
+    assert!(!advanced.enable_complementary(4, Polarity::ActiveHigh));
+}
+
+#[test]
+fn tasks_change_only_enabled_channels() {
+    fake::reset();
+    PWM.init(1_000);
+    assert!(!pwm::set_duty_of(4, 1, 500));
+    PWM.enable(1, Polarity::ActiveHigh);
+    assert!(pwm::set_duty_of(4, 1, 500));
+    assert_eq!(tim::TIM4.ccr1().read(), 4_000);
+    assert!(!pwm::set_duty_of(4, 5, 500));
+    assert!(!pwm::set_duty_of(5, 1, 500));
+}
+
+#[test]
+fn bound_pin_gets_the_alternate_function() {
+    fake::reset();
+    let port = Gpio::at(adresses::GPIOD);
+    assert!(PWM.bind("D", 13, 2));
+    assert_eq!(port.moder().read(), 0b10 << 26);
+    assert_eq!(port.afr(13).read(), 2 << 20);
+    // taken for other drivers now
+    assert!(!PWM.bind("D", 13, 2));
+    assert!(gpiod::PD13::take().is_none());
+    assert!(!PWM.bind("G", 1, 2));
+}
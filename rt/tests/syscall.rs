@@ -0,0 +1,20 @@
+//!
+//! On the host a trap calls the kernel directly,
+//! so the syscall api runs without a context switch.
+//!
+use rt::generic::cpu::c_registers::scb;
+use rt::hal::fake;
+use rt::sys::call_api;
+
+#[test]
+fn stack_usage_of_unknown_task() {
+    assert_eq!(call_api::stack_usage(u32::MAX), None);
+}
+
+#[test]
+fn yield_requests_context_switch() {
+    fake::reset();
+    call_api::yield_task();
+    let written = fake::writes_to(scb::ICSR.adress());
+    assert!(written.iter().any(|v| v & scb::PENDSVSET.mask() != 0));
+}
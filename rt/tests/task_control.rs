@@ -0,0 +1,95 @@
+//!
+//! Scheduling decisions of the task table, run on the host.
+//! The table is global, so every test holds the lock and
+//! starts with an empty table.
+//!
+use rt::sched::task_control;
+use std::sync::{Mutex, MutexGuard};
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+fn setup(tasks: u32) -> MutexGuard<'static, ()> {
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    task_control::reset();
+    for pid in 0..tasks {
+        // the stack pointer only identifies the task here
+        task_control::insert(0x100 * (pid + 1));
+    }
+    guard
+}
+
+#[test]
+fn round_robin_order() {
+    let _guard = setup(3);
+    assert_eq!(task_control::next_process(), 0x200);
+    assert_eq!(task_control::next_process(), 0x300);
+    assert_eq!(task_control::next_process(), 0x100);
+    assert_eq!(task_control::current_pid(), 0);
+}
+
+#[test]
+fn blocked_task_gets_skipped_until_woken() {
+    let _guard = setup(3);
+    task_control::next_process();
+    task_control::block_on(0b1, 0, core::ptr::null_mut());
+
+    assert_eq!(task_control::next_process(), 0x300);
+    assert_eq!(task_control::next_process(), 0x100);
+    assert_eq!(task_control::next_process(), 0x300);
+
+    assert!(task_control::wake_waiting(0b1));
+    assert!(!task_control::wake_waiting(0b1));
+    assert_eq!(task_control::next_process(), 0x100);
+    assert_eq!(task_control::next_process(), 0x200);
+}
+
+#[test]
+fn idle_task_only_runs_without_alternative() {
+    let _guard = setup(3);
+    task_control::set_idle(2);
+
+    assert_eq!(task_control::next_process(), 0x200);
+    assert_eq!(task_control::next_process(), 0x100);
+    task_control::block_on(0b1, 0, core::ptr::null_mut());
+    assert_eq!(task_control::next_process(), 0x200);
+    task_control::block_on(0b1, 0, core::ptr::null_mut());
+
+    assert_eq!(task_control::next_process(), 0x300);
+    assert_eq!(task_control::current_pid(), 2);
+}
+
+#[test]
+fn blocking_call_times_out() {
+    let _guard = setup(2);
+    let mut result = u32::MAX;
+    task_control::next_process();
+    task_control::block_on(0b10, 3, &mut result);
+
+    task_control::tick();
+    task_control::tick();
+    assert_eq!(task_control::next_process(), 0x100);
+    assert_eq!(task_control::next_process(), 0x100);
+    assert_eq!(result, u32::MAX);
+
+    task_control::tick();
+    assert_eq!(result, task_control::WAKE_TIMEOUT);
+    assert_eq!(task_control::next_process(), 0x200);
+}
+
+#[test]
+fn wake_one_hands_over_value() {
+    let _guard = setup(2);
+    let mut result = 0;
+    task_control::next_process();
+    task_control::block_on(0b100, 5, &mut result);
+
+    assert!(task_control::wake_one(0b100, 0x2000_0040));
+    assert_eq!(result, 0x2000_0040);
+    assert!(!task_control::wake_one(0b100, 0x2000_0080));
+
+    // the timeout does not apply anymore
+    for _ in 0..10 {
+        task_control::tick();
+    }
+    assert_eq!(result, 0x2000_0040);
+}
@@ -0,0 +1,115 @@
+//!
+//! Register accesses of the timer driver and the services built on it.
+//!
+use rt::dev::timer::{Mode, TimerX};
+use rt::dev::{measurement, sleep_timer};
+use rt::generic::platform::registers::{rcc, tim};
+use rt::hal::fake;
+
+const TIMER: TimerX = TimerX::new(tim::TIM3);
+
+#[test]
+fn compare_value_gets_replaced() {
+    fake::reset();
+    TIMER.set_compare(1, 500);
+    TIMER.set_compare(1, 20);
+    assert_eq!(tim::TIM3.ccr1().read(), 20);
+    TIMER.set_compare(4, 30);
+    assert_eq!(tim::TIM3.ccr(4).read(), 30);
+}
+
+#[test]
+fn prescaler_gets_replaced() {
+    fake::reset();
+    TIMER.set_prescaler(1000);
+    TIMER.set_prescaler(7);
+    assert_eq!(tim::TIM3.psc().read(), 7);
+}
+
+#[test]
+fn reload_value_fits_counter_width() {
+    fake::reset();
+    TIMER.set_arr(0x1_2345);
+    assert_eq!(tim::TIM3.arr().read(), 0x2345);
+    TimerX::new(tim::TIM2).set_arr(0x1_2345);
+    assert_eq!(tim::TIM2.arr().read(), 0x1_2345);
+}
+
+#[test]
+fn start_and_stop_keep_other_bits() {
+    fake::reset();
+    fake::set(tim::TIM3.cr1().adress(), tim::UDIS.mask());
+    TIMER.start();
+    assert_eq!(tim::TIM3.cr1().read(), tim::UDIS.mask() | tim::CEN.mask());
+    TIMER.stop();
+    assert_eq!(tim::TIM3.cr1().read(), tim::UDIS.mask());
+}
+
+#[test]
+fn mode_selects_one_pulse() {
+    fake::reset();
+    TIMER.set_mode(Mode::OneShot);
+    assert!(tim::TIM3.cr1().is_set(tim::OPM));
+    TIMER.set_mode(Mode::Periodic);
+    assert!(!tim::TIM3.cr1().is_set(tim::OPM));
+}
+
+#[test]
+fn clearing_a_flag_keeps_the_others() {
+    fake::reset();
+    TIMER.clear_update_flag();
+    TIMER.clear_compare_flag(2);
+    let written = fake::writes_to(tim::TIM3.sr().adress());
+    assert_eq!(written, [!tim::UIF.mask(), !tim::cc(2).mask()]);
+}
+
+#[test]
+fn invalid_channels_leave_the_flags_alone() {
+    fake::reset();
+    // channel 0 would hit the update flag
+    fake::set(tim::TIM3.sr().adress(), tim::UIF.mask());
+    assert!(!TIMER.is_compare_pending(0));
+    TIMER.clear_compare_flag(0);
+    TIMER.clear_compare_flag(5);
+    assert!(fake::writes_to(tim::TIM3.sr().adress()).is_empty());
+}
+
+#[test]
+fn interrupts_get_enabled_per_source() {
+    fake::reset();
+    TIMER.enable_update_interrupt();
+    TIMER.enable_compare_interrupt(3);
+    assert_eq!(tim::TIM3.dier().read(), tim::UIE.mask() | tim::cc(3).mask());
+    // tim6 has no channels
+    TimerX::new(tim::TIM6).enable_compare_interrupt(1);
+    assert_eq!(tim::TIM6.dier().read(), 0);
+}
+
+#[test]
+fn reset_pulses_reset_bit() {
+    fake::reset();
+    TimerX::new(tim::TIM2).reset();
+    TIMER.reset();
+    TimerX::new(tim::TIM1).reset();
+    assert_eq!(fake::writes_to(rcc::APB1RSTR.adress()), [0b01, 0b00, 0b10, 0b00]);
+    assert_eq!(fake::writes_to(rcc::APB2RSTR.adress()).len(), 2);
+}
+
+#[test]
+fn sleep_timer_runs_once() {
+    fake::reset();
+    sleep_timer::init(7);
+    sleep_timer::start(400);
+    assert_eq!(tim::TIM3.arr().read(), 400);
+    assert_eq!(tim::TIM3.cr1().read() & (tim::OPM.mask() | tim::CEN.mask()), tim::OPM.mask() | tim::CEN.mask());
+    sleep_timer::acknowledge();
+    assert!(!tim::TIM3.cr1().is_set(tim::CEN));
+}
+
+#[test]
+fn measured_time_in_microseconds() {
+    fake::reset();
+    measurement::start();
+    fake::set(tim::TIM2.cnt().adress(), (0b1 << 31) | 8);
+    assert_eq!(measurement::stop(), (0b1 << 31) | 8);
+}
@@ -0,0 +1,60 @@
+//!
+//! Output of the polled uart driver, recorded by the fake peripherals.
+//!
+use core::fmt::Write;
+use rt::dev::uart;
+use rt::generic::board::BOARD;
+use rt::generic::platform::registers::usart::{self, USART1};
+use rt::hal::fake;
+
+// the driver waits for the transmit register after every byte
+fn setup() {
+    fake::reset();
+    fake::set(USART1.isr().adress(), usart::TXE.mask());
+}
+
+fn transmitted() -> String {
+    fake::writes_to(USART1.tdr().adress()).iter().map(|c| *c as u8 as char).collect()
+}
+
+#[test]
+fn prints_decimal() {
+    setup();
+    uart::print_dec(0);
+    uart::print_str(" ");
+    uart::print_dec(4096);
+    uart::print_str(" ");
+    uart::print_dec(u32::MAX);
+    assert_eq!(transmitted(), "0 4096 4294967295");
+}
+
+#[test]
+fn prints_hex() {
+    setup();
+    uart::print_hex(0xBEEF);
+    assert_eq!(transmitted(), "0x0000BEEF");
+}
+
+#[test]
+fn prints_nul_terminated_string() {
+    setup();
+    let text = b"task\0ignored";
+    unsafe { uart::print_from_ptr(text.as_ptr() as *mut u8) };
+    assert_eq!(transmitted(), "task");
+}
+
+#[test]
+fn formats_through_writer() {
+    setup();
+    write!(uart::Writer, "pid {} at {:#x}", 3, 0x20u32).unwrap();
+    assert_eq!(transmitted(), "pid 3 at 0x20");
+}
+
+#[test]
+fn enables_transmitter_and_receiver() {
+    setup();
+    uart::new(1, 9600).enable();
+    assert_eq!(USART1.brr().read(), BOARD.clock / 9600);
+    let cr1 = USART1.cr1().read();
+    assert_eq!(cr1, usart::UE.mask() | usart::TE.mask() | usart::RE.mask());
+}
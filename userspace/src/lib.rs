@@ -1,2 +1,4 @@
 #![no_std]
 pub mod user;
+#[cfg(feature = "testsets")]
+pub mod testsets;
@@ -0,0 +1,69 @@
+//!
+//! Task sets of the qemu integration tests, see the crate qemutest.
+//! osmain creates one of them instead of the tasks of `user` if
+//! the according test feature is enabled. Every set ends the
+//! emulator with `call_api::exit`, 0 means passed.
+//!
+#![forbid(unsafe_code)]
+
+use rt::sys::call_api;
+
+///
+/// A task sleeps three times while another one keeps the cpu busy.
+///
+pub mod sleep {
+    use super::call_api;
+
+    pub fn sleeper() {
+        call_api::println("sleep start\n\r\0");
+        for _ in 0..3 {
+            call_api::sleep(200);
+            call_api::println("wake up!\n\r\0");
+        }
+        call_api::exit(0);
+    }
+
+    pub fn worker() {
+        loop {
+            call_api::yield_task();
+        }
+    }
+
+    pub const TASKS: [fn(); 2] = [sleeper, worker];
+}
+
+///
+/// Three tasks print their rounds and hand over the cpu after
+/// each one, so they have to appear in round robin order.
+///
+pub mod order {
+    use super::call_api;
+
+    const ROUNDS: usize = 3;
+
+    fn run(lines: [&str; ROUNDS]) {
+        for line in lines.iter() {
+            call_api::println(line);
+            call_api::yield_task();
+        }
+    }
+
+    pub fn task_a() {
+        run(["A0\n\r\0", "A1\n\r\0", "A2\n\r\0"]);
+        call_api::terminate();
+    }
+
+    pub fn task_b() {
+        run(["B0\n\r\0", "B1\n\r\0", "B2\n\r\0"]);
+        call_api::terminate();
+    }
+
+    pub fn task_c() {
+        run(["C0\n\r\0", "C1\n\r\0", "C2\n\r\0"]);
+        // the last task of the set ends the test
+        call_api::sleep(50);
+        call_api::exit(0);
+    }
+
+    pub const TASKS: [fn(); 3] = [task_a, task_b, task_c];
+}
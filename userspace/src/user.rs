@@ -31,9 +31,22 @@ pub fn context1() {
     }
 }
 
+// reports the user button of the discovery board (pa0), which is
+// high while pressed. ends on boards without external interrupts.
+pub fn button() {
+    while call_api::wait_for_edge('A', 0, call_api::EDGE_RISING, 5) {
+        call_api::println("button pressed!\n\r\0");
+    }
+}
+
 pub fn context0() {
+    // ld4 of the discovery board, toggles with every wake up
+    let led = call_api::gpio_claim('E', 11, call_api::GPIO_OUTPUT);
     loop {
         call_api::sleep(500);
+        if led {
+            call_api::gpio_toggle('E', 11);
+        }
         call_api::println("wake up!\n\r\0");
     }
 }